@@ -0,0 +1,238 @@
+use crate::prelude::*;
+use crate::types::{MsgPackError, MsgPackEntry, MsgPackValue};
+use crate::decode::{DEFAULT_MAX_DEPTH, MAX_PREALLOC, at_offset, len2usize, read_timestamp};
+use rmp::Marker;
+
+
+/// A MessagePack entry borrowing its strings and binary from the decoded buffer
+#[derive(Clone, Debug, PartialEq)]
+pub struct MsgPackEntryRef<'a> {
+    /// A raw marker value
+    pub raw_marker: u8,
+    /// The value itself
+    pub data: MsgPackValueRef<'a>,
+}
+impl MsgPackEntryRef<'_> {
+    /// Copies the borrowed data into an owned MsgPackEntry object
+    pub fn to_owned(&self) -> MsgPackEntry {
+        MsgPackEntry::new(self.raw_marker, self.data.to_owned())
+    }
+}
+
+/// Holds an actual type and value, borrowing strings and binary instead of copying them
+#[derive(Clone, Debug, PartialEq)]
+pub enum MsgPackValueRef<'a> {
+    Null,
+    Bool(bool),
+    FixPos(u8), FixNeg(i8),
+    U8(u8), U16(u16), U32(u32), U64(u64),
+    I8(i8), I16(i16), I32(i32), I64(i64),
+    F32(f32), F64(f64),
+    FixStr(&'a str), Str8(&'a str), Str16(&'a str), Str32(&'a str),
+    Bin8(&'a [u8]), Bin16(&'a [u8]), Bin32(&'a [u8]),
+    FixArray(Vec<MsgPackEntryRef<'a>>), Array16(Vec<MsgPackEntryRef<'a>>), Array32(Vec<MsgPackEntryRef<'a>>),
+    FixMap(Vec<(MsgPackEntryRef<'a>, MsgPackEntryRef<'a>)>),
+    Map16(Vec<(MsgPackEntryRef<'a>, MsgPackEntryRef<'a>)>),
+    Map32(Vec<(MsgPackEntryRef<'a>, MsgPackEntryRef<'a>)>),
+    Ext(i8, &'a [u8]), // Extension type and its payload
+    Timestamp { seconds: i64, nanos: u32 }, // Reserved extension type -1
+}
+impl MsgPackValueRef<'_> {
+    /// Copies the borrowed data into an owned MsgPackValue object
+    pub fn to_owned(&self) -> MsgPackValue {
+        let entries = |values: &[MsgPackEntryRef]| values.iter().map(MsgPackEntryRef::to_owned).collect();
+        let pairs = |values: &[(MsgPackEntryRef, MsgPackEntryRef)]| values.iter().map(|(k, v)| (k.to_owned(), v.to_owned())).collect();
+
+        match self {
+            MsgPackValueRef::Null => MsgPackValue::Null,
+            MsgPackValueRef::Bool(b) => MsgPackValue::Bool(*b),
+            MsgPackValueRef::FixPos(n) => MsgPackValue::FixPos(*n),
+            MsgPackValueRef::FixNeg(n) => MsgPackValue::FixNeg(*n),
+            MsgPackValueRef::U8(n)  => MsgPackValue::U8(*n),
+            MsgPackValueRef::U16(n) => MsgPackValue::U16(*n),
+            MsgPackValueRef::U32(n) => MsgPackValue::U32(*n),
+            MsgPackValueRef::U64(n) => MsgPackValue::U64(*n),
+            MsgPackValueRef::I8(n)  => MsgPackValue::I8(*n),
+            MsgPackValueRef::I16(n) => MsgPackValue::I16(*n),
+            MsgPackValueRef::I32(n) => MsgPackValue::I32(*n),
+            MsgPackValueRef::I64(n) => MsgPackValue::I64(*n),
+            MsgPackValueRef::F32(n) => MsgPackValue::F32(*n),
+            MsgPackValueRef::F64(n) => MsgPackValue::F64(*n),
+            MsgPackValueRef::FixStr(s) => MsgPackValue::FixStr(s.to_string()),
+            MsgPackValueRef::Str8(s)   => MsgPackValue::Str8(s.to_string()),
+            MsgPackValueRef::Str16(s)  => MsgPackValue::Str16(s.to_string()),
+            MsgPackValueRef::Str32(s)  => MsgPackValue::Str32(s.to_string()),
+            MsgPackValueRef::Bin8(b)  => MsgPackValue::Bin8(b.to_vec()),
+            MsgPackValueRef::Bin16(b) => MsgPackValue::Bin16(b.to_vec()),
+            MsgPackValueRef::Bin32(b) => MsgPackValue::Bin32(b.to_vec()),
+            MsgPackValueRef::FixArray(values) => MsgPackValue::FixArray(entries(values)),
+            MsgPackValueRef::Array16(values)  => MsgPackValue::Array16(entries(values)),
+            MsgPackValueRef::Array32(values)  => MsgPackValue::Array32(entries(values)),
+            MsgPackValueRef::FixMap(values) => MsgPackValue::FixMap(pairs(values)),
+            MsgPackValueRef::Map16(values)  => MsgPackValue::Map16(pairs(values)),
+            MsgPackValueRef::Map32(values)  => MsgPackValue::Map32(pairs(values)),
+            MsgPackValueRef::Ext(t, b) => MsgPackValue::Ext(*t, b.to_vec()),
+            MsgPackValueRef::Timestamp { seconds, nanos } => MsgPackValue::Timestamp { seconds: *seconds, nanos: *nanos },
+        }
+    }
+}
+
+/// Turns a MessagePack-encoded buffer into a MsgPackEntryRef object that borrows its strings and binary from the buffer
+///
+/// Nothing but the arrays and maps themselves gets allocated, which suits inspecting a few fields of a message
+///
+/// # Examples
+///
+/// ```
+/// use rmpp::MsgPackValueRef;
+///
+/// let input = vec![0x82, 0xA1, 0x61, 0xC4, 0x02, 0xDE, 0xAD, 0xA1, 0x62, 0xD9, 0x02, 0x68, 0x69];
+/// let value = rmpp::unpack_borrowed(&input).unwrap();
+///
+/// let MsgPackValueRef::FixMap(pairs) = &value.data else { panic!() };
+/// let MsgPackValueRef::Bin8(bytes) = pairs[0].1.data else { panic!() };
+/// assert!(core::ptr::eq(bytes, &input[5..7])); // Points into the input, no copy
+/// assert_eq!(MsgPackValueRef::Str8("hi"), pairs[1].1.data);
+///
+/// assert_eq!(rmpp::unpack(&input).unwrap(), value.to_owned());
+/// ```
+pub fn unpack_borrowed(data: &[u8]) -> Result<MsgPackEntryRef<'_>, MsgPackError> {
+    let mut reader = SliceReader { data, depth: 0 };
+
+    reader.read_value().map_err(|e| at_offset(data.len() - reader.data.len(), e))
+}
+
+/// Reads values straight out of a slice, handing out subslices of it
+struct SliceReader<'a> {
+    /// What's left to read
+    data: &'a [u8],
+    /// Current nesting depth of collections
+    depth: usize,
+}
+impl<'a> SliceReader<'a> {
+    /// Splits off exactly `len` bytes
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MsgPackError> {
+        if self.data.len() < len { return Err(MsgPackError::Truncated); }
+        let (res, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(res)
+    }
+
+    /// Splits off exactly `N` bytes
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], MsgPackError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    /// Reads a length field of 1/2/4 bytes
+    fn read_len(&mut self, size: usize) -> Result<usize, MsgPackError> {
+        let len: usize = match size {
+            1 => usize::from(self.take_array::<1>()?[0]),
+            2 => usize::from(u16::from_be_bytes(self.take_array()?)),
+            _ => len2usize(u32::from_be_bytes(self.take_array()?))?,
+        };
+        Ok(len)
+    }
+
+    /// Reads a string of the given length
+    fn read_str(&mut self, len: usize) -> Result<&'a str, MsgPackError> {
+        core::str::from_utf8(self.take(len)?).map_err(|e| MsgPackError::Custom(format!("Invalid UTF-8: {}", e)))
+    }
+
+    /// Reads the elements of an array
+    fn read_array(&mut self, len: usize) -> Result<Vec<MsgPackEntryRef<'a>>, MsgPackError> {
+        self.enter()?;
+        let mut array: Vec<MsgPackEntryRef> = Vec::with_capacity(len.min(MAX_PREALLOC)); // The declared length can't be trusted
+        for _ in 0..len { array.push(self.read_value()?); }
+        self.depth -= 1;
+        Ok(array)
+    }
+
+    /// Reads the key-value pairs of a map
+    fn read_map(&mut self, len: usize) -> Result<Vec<(MsgPackEntryRef<'a>, MsgPackEntryRef<'a>)>, MsgPackError> {
+        self.enter()?;
+        let mut map: Vec<_> = Vec::with_capacity(len.min(MAX_PREALLOC)); // The declared length can't be trusted
+        for _ in 0..len { map.push((self.read_value()?, self.read_value()?)); }
+        self.depth -= 1;
+        Ok(map)
+    }
+
+    /// Reads the type and the data of an extension
+    fn read_ext(&mut self, len: usize) -> Result<MsgPackValueRef<'a>, MsgPackError> {
+        let ext_type: i8 = self.take_array::<1>()?[0] as i8;
+        let buf: &[u8] = self.take(len)?;
+
+        // Extension type -1 is reserved for timestamps
+        if ext_type == -1 && let Some(MsgPackValue::Timestamp { seconds, nanos }) = read_timestamp(buf) {
+            return Ok(MsgPackValueRef::Timestamp { seconds, nanos });
+        }
+
+        Ok(MsgPackValueRef::Ext(ext_type, buf))
+    }
+
+    /// Descends into a collection, failing once the depth limit is exceeded
+    fn enter(&mut self) -> Result<(), MsgPackError> {
+        self.depth += 1;
+        if self.depth > DEFAULT_MAX_DEPTH {
+            return Err(MsgPackError::Custom(format!("Maximum depth of {} exceeded", DEFAULT_MAX_DEPTH)));
+        }
+        Ok(())
+    }
+
+    /// Reads a value, mirroring `read_value` of the owned decoder
+    fn read_value(&mut self) -> Result<MsgPackEntryRef<'a>, MsgPackError> {
+        let raw_marker: u8 = self.take_array::<1>()?[0];
+
+        let value: MsgPackValueRef = match Marker::from_u8(raw_marker) {
+            // Null
+            Marker::Null => MsgPackValueRef::Null,
+            // Boolean
+            Marker::False => MsgPackValueRef::Bool(false),
+            Marker::True  => MsgPackValueRef::Bool(true),
+            // Fixed Integer
+            Marker::FixPos(val) => MsgPackValueRef::FixPos(val),
+            Marker::FixNeg(val) => MsgPackValueRef::FixNeg(val),
+            // Unsigned Integer
+            Marker::U8  => MsgPackValueRef::U8(u8::from_be_bytes(self.take_array()?)),
+            Marker::U16 => MsgPackValueRef::U16(u16::from_be_bytes(self.take_array()?)),
+            Marker::U32 => MsgPackValueRef::U32(u32::from_be_bytes(self.take_array()?)),
+            Marker::U64 => MsgPackValueRef::U64(u64::from_be_bytes(self.take_array()?)),
+            // Signed Integer
+            Marker::I8  => MsgPackValueRef::I8(i8::from_be_bytes(self.take_array()?)),
+            Marker::I16 => MsgPackValueRef::I16(i16::from_be_bytes(self.take_array()?)),
+            Marker::I32 => MsgPackValueRef::I32(i32::from_be_bytes(self.take_array()?)),
+            Marker::I64 => MsgPackValueRef::I64(i64::from_be_bytes(self.take_array()?)),
+            // Float
+            Marker::F32 => MsgPackValueRef::F32(f32::from_be_bytes(self.take_array()?)),
+            Marker::F64 => MsgPackValueRef::F64(f64::from_be_bytes(self.take_array()?)),
+            // String
+            Marker::FixStr(val) => MsgPackValueRef::FixStr(self.read_str(usize::from(val & 0b0001_1111))?),
+            Marker::Str8  => { let len = self.read_len(1)?; MsgPackValueRef::Str8(self.read_str(len)?) },
+            Marker::Str16 => { let len = self.read_len(2)?; MsgPackValueRef::Str16(self.read_str(len)?) },
+            Marker::Str32 => { let len = self.read_len(4)?; MsgPackValueRef::Str32(self.read_str(len)?) },
+            // Binary
+            Marker::Bin8  => { let len = self.read_len(1)?; MsgPackValueRef::Bin8(self.take(len)?) },
+            Marker::Bin16 => { let len = self.read_len(2)?; MsgPackValueRef::Bin16(self.take(len)?) },
+            Marker::Bin32 => { let len = self.read_len(4)?; MsgPackValueRef::Bin32(self.take(len)?) },
+            // Array
+            Marker::FixArray(val) => MsgPackValueRef::FixArray(self.read_array(usize::from(val & 0b0000_1111))?),
+            Marker::Array16 => { let len = self.read_len(2)?; MsgPackValueRef::Array16(self.read_array(len)?) },
+            Marker::Array32 => { let len = self.read_len(4)?; MsgPackValueRef::Array32(self.read_array(len)?) },
+            // Map
+            Marker::FixMap(val) => MsgPackValueRef::FixMap(self.read_map(usize::from(val & 0b0000_1111))?),
+            Marker::Map16 => { let len = self.read_len(2)?; MsgPackValueRef::Map16(self.read_map(len)?) },
+            Marker::Map32 => { let len = self.read_len(4)?; MsgPackValueRef::Map32(self.read_map(len)?) },
+            // Extension
+            Marker::FixExt1  => self.read_ext(1)?,
+            Marker::FixExt2  => self.read_ext(2)?,
+            Marker::FixExt4  => self.read_ext(4)?,
+            Marker::FixExt8  => self.read_ext(8)?,
+            Marker::FixExt16 => self.read_ext(16)?,
+            Marker::Ext8  => { let len = self.read_len(1)?; self.read_ext(len)? },
+            Marker::Ext16 => { let len = self.read_len(2)?; self.read_ext(len)? },
+            Marker::Ext32 => { let len = self.read_len(4)?; self.read_ext(len)? },
+            Marker::Reserved => return Err(MsgPackError::Custom("Reserved marker 0xC1".to_string()))
+        };
+
+        Ok(MsgPackEntryRef { raw_marker, data: value })
+    }
+}
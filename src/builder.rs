@@ -0,0 +1,182 @@
+use crate::prelude::*;
+use crate::types::{MsgPackEntry, MsgPackValue};
+use crate::types::{uint2value, int2value, str2value, bin2value, array2value, map2value};
+
+
+/// Builds MsgPackEntry trees, picking the narrowest marker for every value
+/// 
+/// Every method appends a value, `array` and `map` collect the values appended inside of their closure.
+/// Map values are read as alternating keys and values
+/// 
+/// # Examples
+/// 
+/// ```
+/// let entry = rmpp::MsgPackBuilder::new()
+///     .map(|b| b
+///         .str("name").str("x")
+///         .str("tags").array(|b| b.str("a").int(-1))
+///     )
+///     .build();
+/// 
+/// assert_eq!(
+///     vec![0x82, 0xA4, 0x6E, 0x61, 0x6D, 0x65, 0xA1, 0x78, 0xA4, 0x74, 0x61, 0x67, 0x73, 0x92, 0xA1, 0x61, 0xFF],
+///     rmpp::pack(&entry)
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MsgPackBuilder {
+    entries: Vec<MsgPackEntry>,
+}
+impl MsgPackBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an already built entry as is
+    pub fn entry(mut self, entry: MsgPackEntry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    /// Appends a value as is
+    pub fn value(self, value: MsgPackValue) -> Self {
+        self.entry(MsgPackEntry::from(value))
+    }
+
+    /// Appends a Null
+    pub fn null(self) -> Self {
+        self.value(MsgPackValue::Null)
+    }
+
+    /// Appends a Bool
+    pub fn bool(self, b: bool) -> Self {
+        self.value(MsgPackValue::Bool(b))
+    }
+
+    /// Appends the narrowest integer holding a signed value
+    pub fn int(self, n: i64) -> Self {
+        self.value(int2value(n))
+    }
+
+    /// Appends the narrowest integer holding an unsigned value
+    pub fn uint(self, n: u64) -> Self {
+        self.value(uint2value(n))
+    }
+
+    /// Appends an F32
+    pub fn float32(self, n: f32) -> Self {
+        self.value(MsgPackValue::F32(n))
+    }
+
+    /// Appends an F64
+    pub fn float(self, n: f64) -> Self {
+        self.value(MsgPackValue::F64(n))
+    }
+
+    /// Appends the narrowest string
+    pub fn str(self, s: &str) -> Self {
+        self.value(str2value(s.to_string()))
+    }
+
+    /// Appends the narrowest binary
+    pub fn bin(self, b: &[u8]) -> Self {
+        self.value(bin2value(b.to_vec()))
+    }
+
+    /// Appends an extension
+    pub fn ext(self, ext_type: i8, b: &[u8]) -> Self {
+        self.value(MsgPackValue::Ext(ext_type, b.to_vec()))
+    }
+
+    /// Appends a timestamp
+    pub fn timestamp(self, seconds: i64, nanos: u32) -> Self {
+        self.value(MsgPackValue::Timestamp { seconds, nanos })
+    }
+
+    /// Appends the narrowest array holding the values appended inside of the closure
+    pub fn array<F: FnOnce(MsgPackBuilder) -> MsgPackBuilder>(self, f: F) -> Self {
+        let values: Vec<MsgPackEntry> = f(MsgPackBuilder::new()).entries;
+        self.value(array2value(values))
+    }
+
+    /// Appends the narrowest map holding the values appended inside of the closure as alternating keys and values
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if an odd number of values was appended, leaving a key without a value
+    pub fn map<F: FnOnce(MsgPackBuilder) -> MsgPackBuilder>(self, f: F) -> Self {
+        let values: Vec<MsgPackEntry> = f(MsgPackBuilder::new()).entries;
+        assert!(values.len().is_multiple_of(2), "Map key without a value");
+
+        let mut pairs: Vec<(MsgPackEntry, MsgPackEntry)> = Vec::with_capacity(values.len() / 2);
+        let mut iter = values.into_iter();
+        while let (Some(k), Some(v)) = (iter.next(), iter.next()) { pairs.push((k, v)); }
+
+        self.value(map2value(pairs))
+    }
+
+    /// Returns the single appended value
+    /// 
+    /// # Panics
+    /// 
+    /// Panics unless exactly one value was appended
+    pub fn build(self) -> MsgPackEntry {
+        assert!(self.entries.len() == 1, "Expected exactly one value, got {}", self.entries.len());
+        self.entries.into_iter().next().unwrap()
+    }
+
+    /// Returns all of the appended values
+    pub fn build_all(self) -> Vec<MsgPackEntry> {
+        self.entries
+    }
+}
+
+/// Builds a MsgPackEntry out of a JSON-like literal, picking the narrowest marker for every value
+/// 
+/// Arrays and maps go through `MsgPackBuilder`, `null` becomes Null and 
+///     any other expression is converted with `MsgPackValue::from`
+/// 
+/// # Examples
+/// 
+/// ```
+/// let count: u64 = 3;
+/// let entry = rmpp::msgpack!({ "name": "x", "count": count, "tags": ["a", -1, null] });
+/// 
+/// let expected = rmpp::MsgPackBuilder::new()
+///     .map(|b| b
+///         .str("name").str("x")
+///         .str("count").uint(3)
+///         .str("tags").array(|b| b.str("a").int(-1).null())
+///     )
+///     .build();
+/// assert_eq!(expected, entry);
+/// ```
+#[macro_export]
+macro_rules! msgpack {
+    // Array elements, munching tokens up to a comma
+    (@array $b:expr, ()) => { $b };
+    (@array $b:expr, ($($elem:tt)+)) => { $b.entry($crate::msgpack!($($elem)+)) };
+    (@array $b:expr, ($($elem:tt)+) , $($rest:tt)*) => {
+        $crate::msgpack!(@array $b.entry($crate::msgpack!($($elem)+)), () $($rest)*)
+    };
+    (@array $b:expr, ($($elem:tt)*) $next:tt $($rest:tt)*) => { $crate::msgpack!(@array $b, ($($elem)* $next) $($rest)*) };
+
+    // Map keys, munching tokens up to a colon
+    (@map $b:expr, ()) => { $b };
+    (@map $b:expr, ($($key:tt)+) : $($rest:tt)*) => {
+        $crate::msgpack!(@map_value $b.entry($crate::msgpack!($($key)+)), () $($rest)*)
+    };
+    (@map $b:expr, ($($key:tt)*) $next:tt $($rest:tt)*) => { $crate::msgpack!(@map $b, ($($key)* $next) $($rest)*) };
+
+    // Map values, munching tokens up to a comma
+    (@map_value $b:expr, ($($value:tt)+)) => { $b.entry($crate::msgpack!($($value)+)) };
+    (@map_value $b:expr, ($($value:tt)+) , $($rest:tt)*) => {
+        $crate::msgpack!(@map $b.entry($crate::msgpack!($($value)+)), () $($rest)*)
+    };
+    (@map_value $b:expr, ($($value:tt)*) $next:tt $($rest:tt)*) => { $crate::msgpack!(@map_value $b, ($($value)* $next) $($rest)*) };
+
+    (null) => { $crate::MsgPackBuilder::new().null().build() };
+    ([ $($tt:tt)* ]) => { $crate::MsgPackBuilder::new().array(|b| $crate::msgpack!(@array b, () $($tt)*)).build() };
+    ({ $($tt:tt)* }) => { $crate::MsgPackBuilder::new().map(|b| $crate::msgpack!(@map b, () $($tt)*)).build() };
+    ($e:expr) => { $crate::MsgPackBuilder::new().value($crate::MsgPackValue::from($e)).build() };
+}
@@ -0,0 +1,277 @@
+use crate::prelude::*;
+use serde::de::{self, Deserialize, DeserializeSeed, Visitor, value::SeqDeserializer};
+use serde::forward_to_deserialize_any;
+use crate::types::{MsgPackError, MsgPackEntry, MsgPackValue};
+
+
+/// Extracts any deserializable Rust value out of a MsgPackEntry object
+/// 
+/// - Integers of any marker fit any integer type that can hold them, integral floats fit integer types too
+/// - Strings can be borrowed as `&str`, binary can be borrowed as `&[u8]` or collected into `Vec<u8>` and byte arrays
+/// - Maps fit structs and maps, map keys go through the same rules as values,
+///   so non-string keys work with maps like `HashMap<u32, T>` but can't match struct fields
+/// - Arrays fit sequences and tuples, Null fits unit and None
+/// - Enum variants are read from strings or single-entry maps of `{variant: content}`
+/// - Extensions and timestamps aren't supported
+/// 
+/// # Examples
+/// 
+/// ```
+/// #[derive(serde::Deserialize)]
+/// struct Point<'a> { x: u64, y: f64, name: &'a str }
+/// 
+/// let input = vec![0x83, 0xA1, 0x78, 0xCD, 0x00, 0x01, 0xA1, 0x79, 0x02, 0xA4, 0x6E, 0x61, 0x6D, 0x65, 0xA1, 0x70];
+/// let entry = rmpp::unpack(&input).unwrap();
+/// 
+/// let point: Point = rmpp::from_entry(&entry).unwrap();
+/// assert_eq!((1, 2.0, "p"), (point.x, point.y, point.name));
+/// ```
+pub fn from_entry<'de, T: Deserialize<'de>>(entry: &'de MsgPackEntry) -> Result<T, MsgPackError> {
+    T::deserialize(EntryDeserializer { entry })
+}
+
+impl de::Error for MsgPackError {
+    fn custom<T: core::fmt::Display>(msg: T) -> Self {
+        MsgPackError::Custom(msg.to_string())
+    }
+}
+
+/// Deserializes Rust values out of a borrowed MsgPackEntry object
+struct EntryDeserializer<'de> {
+    entry: &'de MsgPackEntry,
+}
+impl<'de> EntryDeserializer<'de> {
+    /// Visits an integer, converting integral floats
+    fn visit_int<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackError> {
+        match self.entry.data {
+            MsgPackValue::F32(_)|MsgPackValue::F64(_) => {
+                let n: f64 = self.entry.data.as_f64().unwrap();
+                // Casts saturate, so only floats inside i64::MIN..2^63 and 0..2^64 convert exactly
+                let in_range: bool = if n < 0.0 { n >= i64::MIN as f64 } else { n < 1.8446744073709552e19 };
+                // Every float this large is integral, smaller ones survive a trip through i64 (`fract` needs std)
+                let integral: bool = in_range && (n.abs() >= 4503599627370496.0 || n as i64 as f64 == n);
+                if !integral { return de::Deserializer::deserialize_any(self, visitor); }
+
+                if n < 0.0 { visitor.visit_i64(n as i64) } else { visitor.visit_u64(n as u64) }
+            },
+            _ => de::Deserializer::deserialize_any(self, visitor)
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for EntryDeserializer<'de> {
+    type Error = MsgPackError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackError> {
+        match &self.entry.data {
+            // Null
+            MsgPackValue::Null => visitor.visit_unit(),
+            // Boolean
+            MsgPackValue::Bool(b) => visitor.visit_bool(*b),
+            // Integer
+            MsgPackValue::FixPos(_)|MsgPackValue::FixNeg(_)|
+            MsgPackValue::U8(_)|MsgPackValue::U16(_)|MsgPackValue::U32(_)|MsgPackValue::U64(_)|
+            MsgPackValue::I8(_)|MsgPackValue::I16(_)|MsgPackValue::I32(_)|MsgPackValue::I64(_) => {
+                match self.entry.data.as_u64() {
+                    Some(n) => visitor.visit_u64(n),
+                    None => visitor.visit_i64(self.entry.data.as_i64().unwrap()) // Anything that isn't u64 fits i64
+                }
+            },
+            // Float
+            MsgPackValue::F32(n) => visitor.visit_f32(*n),
+            MsgPackValue::F64(n) => visitor.visit_f64(*n),
+            // String
+            MsgPackValue::FixStr(s)|MsgPackValue::Str8(s)|
+            MsgPackValue::Str16(s)|MsgPackValue::Str32(s) => visitor.visit_borrowed_str(s),
+            MsgPackValue::StrRaw(b) => visitor.visit_borrowed_bytes(b),
+            // Binary
+            MsgPackValue::Bin8(b)|MsgPackValue::Bin16(b)|MsgPackValue::Bin32(b) => visitor.visit_borrowed_bytes(b),
+            // Array
+            MsgPackValue::FixArray(values)|MsgPackValue::Array16(values)|MsgPackValue::Array32(values) => {
+                visitor.visit_seq(SeqAccess { iter: values.iter() })
+            },
+            // Map
+            MsgPackValue::FixMap(values)|MsgPackValue::Map16(values)|MsgPackValue::Map32(values) => {
+                visitor.visit_map(MapAccess { iter: values.iter(), value: None })
+            },
+            // Extension
+            MsgPackValue::Ext(_,_)|MsgPackValue::Timestamp { .. } => {
+                Err(MsgPackError::Custom("Extension values can't be deserialized".to_string()))
+            }
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackError> { self.visit_int(visitor) }
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackError> { self.visit_int(visitor) }
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackError> { self.visit_int(visitor) }
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackError> { self.visit_int(visitor) }
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackError> { self.visit_int(visitor) }
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackError> { self.visit_int(visitor) }
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackError> { self.visit_int(visitor) }
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackError> { self.visit_int(visitor) }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackError> {
+        match self.entry.data {
+            MsgPackValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MsgPackError> {
+        // Binary can be collected into byte sequences
+        match self.entry.data.as_bytes() {
+            Some(b) => visitor.visit_seq(SeqDeserializer::new(b.iter().copied())),
+            None => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, MsgPackError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, MsgPackError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self, _name: &'static str, _variants: &'static [&'static str], visitor: V
+    ) -> Result<V::Value, MsgPackError> {
+        match (self.entry.data.as_str(), self.entry.data.as_map()) {
+            // Unit variant
+            (Some(_), _) => visitor.visit_enum(EnumAccess { variant: self.entry, content: None }),
+            // Variant with content
+            (_, Some([(k, v)])) => visitor.visit_enum(EnumAccess { variant: k, content: Some(v) }),
+            _ => Err(MsgPackError::Custom("Expected a string or a single-entry map for an enum".to_string()))
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf unit unit_struct
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Walks array elements
+struct SeqAccess<'de> {
+    iter: core::slice::Iter<'de, MsgPackEntry>,
+}
+impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
+    type Error = MsgPackError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, MsgPackError> {
+        self.iter.next().map(|entry| seed.deserialize(EntryDeserializer { entry })).transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Walks map key-value pairs
+struct MapAccess<'de> {
+    iter: core::slice::Iter<'de, (MsgPackEntry, MsgPackEntry)>,
+    /// Value of the last read key
+    value: Option<&'de MsgPackEntry>,
+}
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
+    type Error = MsgPackError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, MsgPackError> {
+        match self.iter.next() {
+            Some((k, v)) => {
+                self.value = Some(v);
+                seed.deserialize(EntryDeserializer { entry: k }).map(Some)
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, MsgPackError> {
+        let entry: &MsgPackEntry = self.value.take()
+            .ok_or_else(|| MsgPackError::Custom("Map value without a key".to_string()))?;
+        seed.deserialize(EntryDeserializer { entry })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Reads an enum variant and its content
+struct EnumAccess<'de> {
+    variant: &'de MsgPackEntry,
+    content: Option<&'de MsgPackEntry>,
+}
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = MsgPackError;
+    type Variant = VariantAccess<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, VariantAccess<'de>), MsgPackError> {
+        let variant = seed.deserialize(EntryDeserializer { entry: self.variant })?;
+        Ok((variant, VariantAccess { content: self.content }))
+    }
+}
+
+/// Reads the content of an enum variant
+struct VariantAccess<'de> {
+    content: Option<&'de MsgPackEntry>,
+}
+impl<'de> VariantAccess<'de> {
+    fn content(self) -> Result<EntryDeserializer<'de>, MsgPackError> {
+        self.content
+            .map(|entry| EntryDeserializer { entry })
+            .ok_or_else(|| MsgPackError::Custom("Expected an enum variant with content".to_string()))
+    }
+}
+impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
+    type Error = MsgPackError;
+
+    fn unit_variant(self) -> Result<(), MsgPackError> {
+        match self.content {
+            None => Ok(()),
+            Some(entry) => Deserialize::deserialize(EntryDeserializer { entry })
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, MsgPackError> {
+        seed.deserialize(self.content()?)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, MsgPackError> {
+        de::Deserializer::deserialize_seq(self.content()?, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self, _fields: &'static [&'static str], visitor: V
+    ) -> Result<V::Value, MsgPackError> {
+        de::Deserializer::deserialize_map(self.content()?, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floats_out_of_integer_range() {
+        for n in [1e30, -1e30, 1.8446744073709552e19, -9.3e18, f64::INFINITY, f64::NAN] {
+            let entry = MsgPackEntry::from(MsgPackValue::F64(n));
+            let err = from_entry::<i64>(&entry).unwrap_err().to_string();
+            assert!(err.starts_with("invalid type: floating point"), "{}: {}", n, err);
+            assert!(from_entry::<u64>(&entry).is_err(), "{}", n);
+        }
+    }
+
+    #[test]
+    fn floats_at_integer_range_bounds() {
+        let entry = MsgPackEntry::from(MsgPackValue::F64(i64::MIN as f64));
+        assert_eq!(i64::MIN, from_entry::<i64>(&entry).unwrap());
+
+        let entry = MsgPackEntry::from(MsgPackValue::F64(9.223372036854776e18));
+        assert_eq!(1 << 63, from_entry::<u64>(&entry).unwrap());
+        assert!(from_entry::<i64>(&entry).is_err());
+
+        let entry = MsgPackEntry::from(MsgPackValue::F64((u64::MAX - 2047) as f64));
+        assert_eq!(u64::MAX - 2047, from_entry::<u64>(&entry).unwrap());
+    }
+}
\ No newline at end of file
@@ -1,56 +1,1378 @@
-use crate::types::{MsgPackError, MsgPackValue, MsgPackEntry};
-use byteorder::{ReadBytesExt, BigEndian};
+use crate::prelude::*;
+use crate::types::{MsgPackError, MsgPackValue, MsgPackEntry, BasicTypes, JsonError, json_error, uint2value, int2value};
+use crate::json::{bin_to_hex, truncate_fields};
+#[cfg(feature = "std")]
+use crate::json::to_json_string_indent;
+use crate::io::{self, Cursor, Read, ReadBytesExt, BigEndian};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
-use std::io::{Cursor, Read};
 use rmp::Marker;
 
 
+/// Upper bound for pre-allocation (in bytes or elements) based on an untrusted declared length
+pub(crate) const MAX_PREALLOC: usize = 4096;
+
+/// Default maximum nesting depth of arrays and maps
+pub const DEFAULT_MAX_DEPTH: usize = 1024;
+
+/// Handling of strings that aren't valid UTF-8, see `unpack_with_utf8`
+#[derive(Clone, Copy, Debug)]
+pub enum Utf8Policy {
+    /// Fail the decode
+    Strict,
+    /// Replace invalid sequences with U+FFFD
+    Lossy,
+    /// Keep the raw bytes as StrRaw
+    Raw,
+    /// Hand the raw bytes to a function that either repairs them into a string or fails the decode, 
+    ///     e.g. to read the CESU-8 of legacy JVM producers
+    Custom(fn(&[u8]) -> Result<String, MsgPackError>),
+}
+
+/// Holds the decoding limits and the current decoding progress
+struct ReadState {
+    /// Maximum nesting depth of collections
+    max_depth: usize,
+    /// Current nesting depth of collections
+    depth: usize,
+    /// What happens to strings that aren't valid UTF-8
+    utf8: Utf8Policy,
+    /// Whether maps with logically equal keys are rejected
+    strict_maps: bool,
+    /// Whether integers are narrowed to the smallest marker as they're read
+    normalize_ints: bool,
+    /// Maximum number of bytes the decoded values may take up
+    max_alloc: usize,
+    /// Number of bytes the decoded values take up so far
+    allocated: usize,
+    /// Maximum number of values, collections and their elements included
+    max_nodes: usize,
+    /// Number of values read so far
+    nodes: usize,
+    /// Buffers of a previously decoded value to be reused for strings, binary and extensions
+    spare_bytes: Vec<Vec<u8>>,
+    /// Buffers of a previously decoded value to be reused for arrays
+    spare_arrays: Vec<Vec<MsgPackEntry>>,
+    /// Buffers of a previously decoded value to be reused for maps
+    spare_maps: Vec<Vec<(MsgPackEntry, MsgPackEntry)>>,
+}
+impl ReadState {
+    fn new(max_depth: usize) -> Self {
+        Self { max_depth, depth: 0, utf8: Utf8Policy::Strict, strict_maps: false, normalize_ints: false, max_alloc: usize::MAX, allocated: 0, max_nodes: usize::MAX, nodes: 0, spare_bytes: vec![], spare_arrays: vec![], spare_maps: vec![] }
+    }
+
+    /// Accounts for memory about to be allocated, failing once the budget is exceeded
+    fn alloc(&mut self, bytes: usize) -> Result<(), MsgPackError> {
+        self.allocated = self.allocated.saturating_add(bytes);
+        if self.allocated > self.max_alloc {
+            return Err(MsgPackError::Custom(format!("Allocation budget of {} bytes exceeded", self.max_alloc)));
+        }
+        Ok(())
+    }
+
+    /// Accounts for a value about to be read, failing once the node limit is exceeded
+    fn node(&mut self) -> Result<(), MsgPackError> {
+        self.nodes += 1;
+        if self.nodes > self.max_nodes {
+            return Err(MsgPackError::Custom(format!("Maximum of {} values exceeded", self.max_nodes)));
+        }
+        Ok(())
+    }
+
+    /// Takes apart a value, keeping its buffers to be reused in the order the same shape would ask for them
+    fn recycle(&mut self, value: MsgPackValue) {
+        match value {
+            MsgPackValue::FixStr(s)|MsgPackValue::Str8(s)|MsgPackValue::Str16(s)|MsgPackValue::Str32(s) => {
+                self.spare_bytes.push(s.into_bytes());
+            },
+            MsgPackValue::StrRaw(b)|MsgPackValue::Bin8(b)|MsgPackValue::Bin16(b)|MsgPackValue::Bin32(b)|MsgPackValue::Ext(_, b) => {
+                self.spare_bytes.push(b);
+            },
+            MsgPackValue::FixArray(mut values)|MsgPackValue::Array16(mut values)|MsgPackValue::Array32(mut values) => {
+                // The array goes before its elements, just like it gets decoded
+                let index: usize = self.spare_arrays.len();
+                self.spare_arrays.push(vec![]);
+                for v in values.drain(..) { self.recycle(v.data); }
+                self.spare_arrays[index] = values;
+            },
+            MsgPackValue::FixMap(mut values)|MsgPackValue::Map16(mut values)|MsgPackValue::Map32(mut values) => {
+                let index: usize = self.spare_maps.len();
+                self.spare_maps.push(vec![]);
+                for (k, v) in values.drain(..) { self.recycle(k.data); self.recycle(v.data); }
+                self.spare_maps[index] = values;
+            },
+            _ => {}
+        }
+    }
+
+    /// Descends into a collection, failing once the depth limit is exceeded
+    fn enter(&mut self) -> Result<(), MsgPackError> {
+        self.depth += 1;
+        if self.depth > self.max_depth {
+            return Err(MsgPackError::Custom(format!("Maximum depth of {} exceeded", self.max_depth)));
+        }
+        Ok(())
+    }
+
+    /// Ascends out of a collection
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+}
+
+
 /// Turns a MessagePack-encoded buffer into a json-encoded MsgPackEntry string
 /// 
+/// Maps are arrays of `[key, value]` pairs kept in wire order, decoding and packing never reorder them. 
+/// Only `pack_canonical` sorts keys, and only when asked to
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0xC3];
+/// let value = rmpp::unpack_json(&input, Some(false)).unwrap();
+/// 
+/// assert_eq!(
+///     r###"{"raw_marker":195,"basic_type":"Bool","data":{"type":"Bool","value":true}}"###, 
+///     value
+/// );
+/// 
+/// // NaN and infinity are represented as string sentinels
+/// let input = vec![0xCB, 0x7F, 0xF8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+/// let json = rmpp::unpack_json(&input, Some(false)).unwrap();
+/// 
+/// assert!(json.contains(r###""value":"NaN""###));
+/// 
+/// // Map keys keep their wire order all the way through
+/// let input = vec![0x83, 0xA1, 0x7A, 0x01, 0xA1, 0x61, 0x02, 0xA1, 0x6D, 0x03];
+/// let json = rmpp::unpack_json(&input, Some(false)).unwrap();
+/// 
+/// let (z, a, m) = (json.find(r###""z""###).unwrap(), json.find(r###""a""###).unwrap(), json.find(r###""m""###).unwrap());
+/// assert!(z < a && a < m);
+/// assert_eq!(input, rmpp::pack_json(&json).unwrap());
+/// ```
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn unpack_json(data: &[u8], pretty: Option<bool>) -> Result<String, JsonError> {
+    let value = read_slice(data, &mut ReadState::new(DEFAULT_MAX_DEPTH))
+        .map_err(json_error)?;
+
+    if pretty.unwrap_or(false) { serde_json::to_string_pretty(&value) } else { serde_json::to_string(&value) } 
+        .map_err(json_error)
+}
+
+/// Turns a MessagePack-encoded buffer into a json-encoded MsgPackEntry written straight to a writer, e.g. a file or a socket
+/// 
+/// The output is the same as `unpack_json`'s. The whole value is still decoded before anything is written, 
+///     but the json itself is never held in memory
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0xC3];
+/// let mut output: Vec<u8> = vec![];
+/// rmpp::unpack_json_to_writer(&input, &mut output, false).unwrap();
+/// 
+/// assert_eq!(rmpp::unpack_json(&input, Some(false)).unwrap().as_bytes(), output);
+/// ```
+#[cfg(feature = "std")]
+pub fn unpack_json_to_writer<W: io::Write>(data: &[u8], writer: &mut W, pretty: bool) -> Result<(), MsgPackError> {
+    let value = read_slice(data, &mut ReadState::new(DEFAULT_MAX_DEPTH))?;
+
+    if pretty { serde_json::to_writer_pretty(writer, &value) } else { serde_json::to_writer(writer, &value) }
+        .map_err(|e| if e.is_io() { MsgPackError::Io(e.into()) } else { MsgPackError::Custom(e.to_string()) })
+}
+
+/// Turns a MessagePack-encoded buffer into a json-encoded MsgPackEntry string, pretty-printed with the given indent
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0xC3];
+/// let value = rmpp::unpack_json_indent(&input, "\t").unwrap();
+/// 
+/// assert!(value.starts_with("{\n\t\"raw_marker\": 195,\n\t\"basic_type\""));
+/// 
+/// let value = rmpp::unpack_json_indent(&input, "    ").unwrap();
+/// assert!(value.contains("\n    \"data\": {\n        \"type\": \"Bool\""));
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn unpack_json_indent(data: &[u8], indent: &str) -> Result<String, JsonError> {
+    let value = read_slice(data, &mut ReadState::new(DEFAULT_MAX_DEPTH))
+        .map_err(json_error)?;
+
+    to_json_string_indent(&value, indent).map_err(json_error)
+}
+
+/// Turns a MessagePack-encoded buffer into a json-encoded MsgPackEntry string, optionally rendering binary as hex strings
+/// 
+/// `pack_json` reads binary from both the byte arrays and the hex strings
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0xC4, 0x04, 0xDE, 0xAD, 0xBE, 0xEF];
+/// let json = rmpp::unpack_json_opts(&input, Some(false), Some(true)).unwrap();
+/// 
+/// assert!(json.contains(r###""value":"deadbeef""###));
+/// assert_eq!(input, rmpp::pack_json(&json).unwrap());
+/// ```
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn unpack_json_opts(data: &[u8], pretty: Option<bool>, bin_hex: Option<bool>) -> Result<String, JsonError> {
+    let value = read_slice(data, &mut ReadState::new(DEFAULT_MAX_DEPTH))
+        .map_err(json_error)?;
+
+    let mut json = serde_json::to_value(&value).map_err(json_error)?;
+    if bin_hex.unwrap_or(false) { bin_to_hex(&mut json); }
+
+    if pretty.unwrap_or(false) { serde_json::to_string_pretty(&json) } else { serde_json::to_string(&json) } 
+        .map_err(json_error)
+}
+
+/// Turns a MessagePack-encoded buffer into plain json holding just the data, without any of the type metadata
+/// 
+/// The data view next to `unpack_json`'s typed one, see `MsgPackValue::to_json_value` for how values collapse: 
+///     numbers and strings stay themselves, binary becomes `{"$bin": "<base64>"}`, 
+///     strings that aren't valid UTF-8 become `{"$str_raw": "<base64>"}` and so on. 
+/// The output can't be packed back, `pack_json` only takes the typed format
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0x83, 0xA1, 0x61, 0xCD, 0x01, 0x00, 0xA1, 0x62, 0x92, 0xA1, 0x78, 0xC3, 0xA1, 0x63, 0xC4, 0x02, 0xDE, 0xAD];
+/// 
+/// let json = rmpp::unpack_plain_json(&input, Some(false)).unwrap();
+/// assert_eq!(r###"{"a":256,"b":["x",true],"c":{"$bin":"3q0="}}"###, json);
+/// 
+/// let json = rmpp::unpack_plain_json(&input, Some(true)).unwrap();
+/// assert!(json.starts_with("{\n  \"a\": 256,"));
+/// ```
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn unpack_plain_json(data: &[u8], pretty: Option<bool>) -> Result<String, JsonError> {
+    let json = read_slice(data, &mut ReadState::new(DEFAULT_MAX_DEPTH))
+        .map_err(json_error)?
+        .to_json_value();
+
+    if pretty.unwrap_or(false) { serde_json::to_string_pretty(&json) } else { serde_json::to_string(&json) } 
+        .map_err(json_error)
+}
+
+/// Turns a MessagePack-encoded buffer into a pretty-printed json-encoded MsgPackEntry string 
+///     with long strings and binary cut down to a preview
+/// 
+/// Strings keep their first `max_field_len` bytes and byte arrays their first `max_field_len` elements, 
+///     followed by a `...(N more)` note. Meant for logs and inspection, the output doesn't pack back to the original value
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = [&[0x92, 0xC5, 0x01, 0x00][..], &[0xAB; 256], &[0xA6], b"abcdef"].concat();
+/// let json = rmpp::unpack_json_preview(&input, 4).unwrap();
+/// 
+/// let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+/// 
+/// let (bin, str) = (&json["data"]["value"][0]["data"], &json["data"]["value"][1]["data"]);
+/// assert_eq!(serde_json::json!({"type": "Bin16", "value": [171, 171, 171, 171, "...(252 more)"]}), *bin);
+/// assert_eq!(serde_json::json!({"type": "FixStr", "value": "abcd...(2 more)"}), *str);
+/// ```
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn unpack_json_preview(data: &[u8], max_field_len: usize) -> Result<String, JsonError> {
+    let value = read_slice(data, &mut ReadState::new(DEFAULT_MAX_DEPTH))
+        .map_err(json_error)?;
+
+    let mut json = serde_json::to_value(&value).map_err(json_error)?;
+    truncate_fields(&mut json, max_field_len, false);
+
+    serde_json::to_string_pretty(&json).map_err(json_error)
+}
+
+/// Turns a MessagePack-encoded buffer into a json-encoded MsgPackEntry string, 
+///     where every entry also carries the `byte_offset` and `byte_len` of its encoded value
+/// 
+/// Offsets of map keys and values, array elements and nested collections all point into the input buffer
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0x81, 0xA1, 0x61, 0x92, 0xC3, 0xCD, 0x01, 0x00];
+/// let json = rmpp::unpack_json_with_offsets(&input, Some(false)).unwrap();
+/// let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+/// 
+/// assert_eq!((0, 8), (json["byte_offset"].as_u64().unwrap(), json["byte_len"].as_u64().unwrap()));
+/// 
+/// let [key, value] = &json["data"]["value"][0].as_array().unwrap()[..] else { panic!() };
+/// assert_eq!((1, 2), (key["byte_offset"].as_u64().unwrap(), key["byte_len"].as_u64().unwrap()));
+/// assert_eq!((3, 5), (value["byte_offset"].as_u64().unwrap(), value["byte_len"].as_u64().unwrap()));
+/// 
+/// let element = &value["data"]["value"][1];
+/// assert_eq!((5, 3), (element["byte_offset"].as_u64().unwrap(), element["byte_len"].as_u64().unwrap()));
+/// ```
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn unpack_json_with_offsets(data: &[u8], pretty: Option<bool>) -> Result<String, JsonError> {
+    let value = read_slice(data, &mut ReadState::new(DEFAULT_MAX_DEPTH))
+        .map_err(json_error)?;
+
+    let mut json = serde_json::to_value(&value).map_err(json_error)?;
+    add_offsets(&mut json, data, &mut 0).map_err(json_error)?;
+
+    if pretty.unwrap_or(false) { serde_json::to_string_pretty(&json) } else { serde_json::to_string(&json) } 
+        .map_err(json_error)
+}
+
+/// Adds the offset and the length of the value at `pos` to its json entry, then does the same for its children
+/// 
+/// Headers come in the same order the entries were decoded in, so they're walked side by side
+fn add_offsets(json: &mut serde_json::Value, data: &[u8], pos: &mut usize) -> Result<(), MsgPackError> {
+    let start: usize = *pos;
+    let (len, _) = skip_header(&mut &data[start..])?;
+    *pos += len;
+
+    if matches!(json["basic_type"].as_str(), Some("Array"|"Map")) && let Some(values) = json["data"]["value"].as_array_mut() {
+        for value in values {
+            match value.as_array_mut() {
+                Some(pair) => for entry in pair { add_offsets(entry, data, pos)?; }, // Map key and value
+                None => add_offsets(value, data, pos)?
+            }
+        }
+    }
+
+    json["byte_offset"] = serde_json::Value::from(start);
+    json["byte_len"] = serde_json::Value::from(*pos - start);
+    Ok(())
+}
+
+/// A json-encoded MsgPackEntry string along with the offset of the value that follows it
+#[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FramedJson {
+    /// The json-encoded MsgPackEntry
+    pub json: String,
+    /// Offset right after the decoded value
+    pub next_offset: usize,
+}
+
+/// Turns a MessagePack value found at the given offset of a buffer into a json-encoded MsgPackEntry string, 
+///     also returning the offset right after the value
+/// 
+/// Lets JS loop through a buffer holding several concatenated values
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0xC3, 0xC2];
+/// let framed = rmpp::unpack_json_framed(&input, 1).unwrap();
+/// 
+/// assert_eq!(
+///     r###"{"raw_marker":194,"basic_type":"Bool","data":{"type":"Bool","value":false}}"###, 
+///     framed.json
+/// );
+/// assert_eq!(2, framed.next_offset);
+/// ```
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn unpack_json_framed(data: &[u8], offset: usize) -> Result<FramedJson, JsonError> {
+    let (value, next_offset) = unpack_at(data, offset).map_err(json_error)?;
+    let json: String = serde_json::to_string(&value).map_err(json_error)?;
+    Ok(FramedJson { json, next_offset })
+}
+
+/// Turns a MessagePack-encoded buffer into a MsgPackEntry object
+/// 
+/// An empty buffer is reported as `MsgPackError::Truncated`, just like a buffer ending mid-value
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0xC3];
+/// let value = rmpp::unpack(&input).unwrap();
+/// 
+/// let entry = rmpp::MsgPackEntry::new(
+///     195, rmpp::MsgPackValue::Bool(true)
+/// );
+/// 
+/// assert_eq!(entry, value);
+/// 
+/// let input = vec![0xD4, 0x01, 0xFF];
+/// let value = rmpp::unpack(&input).unwrap();
+/// 
+/// assert_eq!(rmpp::MsgPackValue::Ext(1, vec![0xFF]), value.data);
+/// 
+/// let input = vec![0xD7, 0xFF, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01];
+/// let value = rmpp::unpack(&input).unwrap();
+/// 
+/// assert_eq!(rmpp::MsgPackValue::Timestamp { seconds: 1, nanos: 1 }, value.data);
+/// 
+/// // A Str32 claiming a 4GB length fails without allocating 4GB
+/// let input = vec![0xDB, 0xFF, 0xFF, 0xFF, 0xFF, 0x61];
+/// assert!(rmpp::unpack(&input).is_err());
+/// 
+/// // So does an Array32 claiming 4 billion elements
+/// let input = vec![0xDD, 0xFF, 0xFF, 0xFF, 0xFF, 0xC0];
+/// assert!(rmpp::unpack(&input).is_err());
+/// 
+/// // The reserved marker is an error rather than a panic, even nested
+/// let input = vec![0x91, 0xC1];
+/// assert_eq!("Reserved marker 0xC1 at offset 2", rmpp::unpack(&input).unwrap_err().to_string());
+/// ```
+pub fn unpack(data: &[u8]) -> Result<MsgPackEntry, MsgPackError> {
+    unpack_with_limit(data, DEFAULT_MAX_DEPTH)
+}
+
+/// Turns a MessagePack-encoded buffer into a MsgPackEntry object, limiting the nesting depth of collections
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0x91, 0x91, 0xC3];
+/// 
+/// assert!(rmpp::unpack_with_limit(&input, 2).is_ok());
+/// assert!(rmpp::unpack_with_limit(&input, 1).is_err());
+/// ```
+pub fn unpack_with_limit(data: &[u8], depth: usize) -> Result<MsgPackEntry, MsgPackError> {
+    read_slice(data, &mut ReadState::new(depth))
+}
+
+/// Reads a single MessagePack value straight out of a reader and returns a MsgPackEntry object
+/// 
+/// Handy for decoding from a file or a socket without buffering everything first
+/// 
+/// # Examples 
+/// 
+/// ```
+/// // Byte slices are readers too, with or without `std`
+/// let mut reader: &[u8] = &[0xC3, 0xC2];
+/// 
+/// assert_eq!(rmpp::MsgPackValue::Bool(true), rmpp::unpack_from(&mut reader).unwrap().data);
+/// assert_eq!(rmpp::MsgPackValue::Bool(false), rmpp::unpack_from(&mut reader).unwrap().data);
+/// ```
+pub fn unpack_from<R: Read>(reader: &mut R) -> Result<MsgPackEntry, MsgPackError> {
+    read_value(reader, &mut ReadState::new(DEFAULT_MAX_DEPTH))
+}
+
+/// Reads a single MessagePack value out of an async reader and returns a MsgPackEntry object
+/// 
+/// The async counterpart of `unpack_from`. The bytes of the value are collected first, following its headers 
+///     through however many reads they arrive in, then decoded with the same limits as `unpack`. 
+/// Headers are read a byte at a time, so unbuffered readers are best wrapped in `tokio::io::BufReader`. 
+/// A reader ending mid-value is reported as `MsgPackError::Truncated`, other errors carry the offset into the value like `unpack`'s
+/// 
+/// # Examples 
+/// 
+/// ```
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let mut reader = tokio::io::BufReader::new(&[0x92, 0xA2, 0x68, 0x69, 0xC3, 0xC2][..]);
+/// 
+///     assert_eq!(rmpp::msgpack!(["hi", true]), rmpp::unpack_async(&mut reader).await.unwrap());
+///     assert_eq!(rmpp::msgpack!(false), rmpp::unpack_async(&mut reader).await.unwrap());
+///     assert!(matches!(rmpp::unpack_async(&mut reader).await, Err(rmpp::MsgPackError::Truncated)));
+/// }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn unpack_async<R: tokio::io::AsyncRead + Unpin>(reader: &mut R) -> Result<MsgPackEntry, MsgPackError> {
+    let mut buf: Vec<u8> = vec![];
+    collect_value_async(reader, &mut buf).await.map_err(|e| at_offset(buf.len(), e))?;
+
+    read_slice(&buf, &mut ReadState::new(DEFAULT_MAX_DEPTH))
+}
+
+/// Appends the bytes of a single MessagePack value read out of an async reader to the buffer
+#[cfg(feature = "tokio")]
+async fn collect_value_async<R: tokio::io::AsyncRead + Unpin>(reader: &mut R, buf: &mut Vec<u8>) -> Result<(), MsgPackError> {
+    use tokio::io::AsyncReadExt;
+
+    let mut remaining: u64 = 1;
+    while remaining > 0 {
+        let start: usize = buf.len();
+        let (payload_len, children) = loop {
+            buf.push(reader.read_u8().await?);
+            match read_header(&mut &buf[start..]) {
+                Ok((_, payload_len, children)) => break (payload_len, children),
+                Err(MsgPackError::Truncated) => continue, // The length field follows the marker
+                Err(e) => return Err(e)
+            }
+        };
+
+        // The buffer only grows as the data arrives, so a bogus length can't allocate more than what was sent
+        let read: usize = (&mut *reader).take(payload_len as u64).read_to_end(buf).await?;
+        if read < payload_len { return Err(MsgPackError::Truncated); }
+
+        remaining = remaining - 1 + children;
+    }
+
+    Ok(())
+}
+
+/// Turns a MessagePack-encoded buffer into a MsgPackEntry object, replacing invalid UTF-8 in strings with U+FFFD
+/// 
+/// Meant for inspection: strings with replaced sequences no longer re-encode to the original bytes
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0xA2, 0x61, 0xFF];
+/// let value = rmpp::unpack_lossy(&input).unwrap();
+/// 
+/// assert_eq!(rmpp::MsgPackValue::FixStr("a\u{FFFD}".to_string()), value.data);
+/// assert!(rmpp::unpack(&input).is_err());
+/// ```
+pub fn unpack_lossy(data: &[u8]) -> Result<MsgPackEntry, MsgPackError> {
+    let mut state = ReadState::new(DEFAULT_MAX_DEPTH);
+    state.utf8 = Utf8Policy::Lossy;
+    read_slice(data, &mut state)
+}
+
+/// Turns a MessagePack-encoded buffer into a MsgPackEntry object, keeping strings that aren't valid UTF-8 as raw bytes
+/// 
+/// Such strings become StrRaw values, which re-encode with the narrowest string marker, 
+///     serialize to typed JSON as base64 and collapse to `{"$str_raw": "<base64>"}` in plain JSON
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0xA2, 0x61, 0xFF];
+/// let value = rmpp::unpack_raw_str(&input).unwrap();
+/// 
+/// assert_eq!(rmpp::MsgPackValue::StrRaw(vec![0x61, 0xFF]), value.data);
+/// assert_eq!(input, rmpp::pack(&value));
+/// 
+/// let json = serde_json::to_string(&value).unwrap();
+/// assert_eq!(input, rmpp::pack_json(&json).unwrap());
+/// ```
+pub fn unpack_raw_str(data: &[u8]) -> Result<MsgPackEntry, MsgPackError> {
+    let mut state = ReadState::new(DEFAULT_MAX_DEPTH);
+    state.utf8 = Utf8Policy::Raw;
+    read_slice(data, &mut state)
+}
+
+/// Turns a MessagePack-encoded buffer into a MsgPackEntry object, handling strings that aren't valid UTF-8 by the policy
+/// 
+/// Valid strings never reach the policy, so `Custom` functions only ever see the bytes `unpack` would reject, 
+///     surrogates and overlong sequences included. Their errors fail the decode as is
+/// 
+/// # Examples 
+/// 
+/// ```
+/// use rmpp::{MsgPackError, MsgPackValue, Utf8Policy};
+/// 
+/// // Repairs the surrogate pairs of CESU-8, which Java producers emit for characters beyond U+FFFF
+/// fn cesu8(bytes: &[u8]) -> Result<String, MsgPackError> {
+///     let mut s = String::new();
+///     let mut rest = bytes;
+///     while !rest.is_empty() {
+///         if let [0xED, a @ 0xA0..=0xAF, b, 0xED, c @ 0xB0..=0xBF, d, tail @ ..] = rest {
+///             let high = (u32::from(a & 0x0F) << 6) | u32::from(b & 0x3F);
+///             let low = (u32::from(c & 0x0F) << 6) | u32::from(d & 0x3F);
+///             s.push(char::from_u32(0x10000 + (high << 10) + low).unwrap());
+///             rest = tail;
+///         } else {
+///             let end = rest.iter().skip(1).position(|b| *b == 0xED).map_or(rest.len(), |i| i + 1);
+///             s += std::str::from_utf8(&rest[..end]).map_err(|e| MsgPackError::Custom(e.to_string()))?;
+///             rest = &rest[end..];
+///         }
+///     }
+///     Ok(s)
+/// }
+/// 
+/// let input = vec![0xA7, 0x61, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80];
+/// let value = rmpp::unpack_with_utf8(&input, Utf8Policy::Custom(cesu8)).unwrap();
+/// 
+/// assert_eq!(MsgPackValue::FixStr("a\u{1F600}".to_string()), value.data);
+/// assert!(rmpp::unpack(&input).is_err());
+/// assert!(rmpp::unpack_with_utf8(&[0xA2, 0x61, 0xFF], Utf8Policy::Custom(cesu8)).is_err());
+/// ```
+pub fn unpack_with_utf8(data: &[u8], policy: Utf8Policy) -> Result<MsgPackEntry, MsgPackError> {
+    let mut state = ReadState::new(DEFAULT_MAX_DEPTH);
+    state.utf8 = policy;
+    read_slice(data, &mut state)
+}
+
+/// Turns a MessagePack-encoded buffer into a MsgPackEntry object, limiting the total memory the decoded values may take up
+/// 
+/// The budget covers the data of strings, binary and extensions along with the elements of arrays and maps, 
+///     and is charged by the declared length before anything is read
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0x92, 0xA3, 0x61, 0x62, 0x63, 0xC4, 0x02, 0x00, 0x01];
+/// let array_size = 2 * std::mem::size_of::<rmpp::MsgPackEntry>();
+/// 
+/// assert!(rmpp::unpack_with_budget(&input, array_size + 5).is_ok());
+/// assert!(rmpp::unpack_with_budget(&input, array_size + 4).is_err());
+/// 
+/// // Many small collections add up too
+/// let input = [&[0xDC, 0x00, 0x64][..], &[0x91, 0xC0].repeat(100)].concat();
+/// let entry_size = std::mem::size_of::<rmpp::MsgPackEntry>();
+/// 
+/// assert!(rmpp::unpack_with_budget(&input, 200 * entry_size).is_ok());
+/// assert!(rmpp::unpack_with_budget(&input, 150 * entry_size).is_err());
+/// ```
+pub fn unpack_with_budget(data: &[u8], max_bytes: usize) -> Result<MsgPackEntry, MsgPackError> {
+    let mut state = ReadState::new(DEFAULT_MAX_DEPTH);
+    state.max_alloc = max_bytes;
+    read_slice(data, &mut state)
+}
+
+/// Turns a MessagePack-encoded buffer into a MsgPackEntry object, limiting the total number of values decoded
+/// 
+/// Every value counts, be it a collection, an element or a map key, so a huge but flat array is rejected too
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0x92, 0x91, 0xC0, 0xC3];
+/// 
+/// assert!(rmpp::unpack_with_max_nodes(&input, 4).is_ok());
+/// assert!(rmpp::unpack_with_max_nodes(&input, 3).is_err());
+/// 
+/// // A million Nulls fit in a megabyte and get stopped early
+/// let input = [&[0xDD, 0x00, 0x0F, 0x42, 0x40][..], &[0xC0; 1_000_000]].concat();
+/// assert!(rmpp::unpack_with_max_nodes(&input, 1000).is_err());
+/// ```
+pub fn unpack_with_max_nodes(data: &[u8], max_nodes: usize) -> Result<MsgPackEntry, MsgPackError> {
+    let mut state = ReadState::new(DEFAULT_MAX_DEPTH);
+    state.max_nodes = max_nodes;
+    read_slice(data, &mut state)
+}
+
+/// Turns a MessagePack-encoded buffer into a MsgPackEntry object, failing if any map holds logically equal keys
+/// 
+/// Keys are compared with `MsgPackValue::logically_eq`, so the same string under different markers is a duplicate too. 
+/// Parsers disagree on which of the duplicates wins, which makes such maps ambiguous
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0x82, 0xA1, 0x61, 0x01, 0xD9, 0x01, 0x61, 0x02];
+/// 
+/// assert!(rmpp::unpack(&input).is_ok());
+/// assert_eq!("Duplicate map key: \"a\" at offset 8", rmpp::unpack_strict_maps(&input).unwrap_err().to_string());
+/// 
+/// let input = vec![0x82, 0x01, 0xC0, 0xCC, 0x01, 0xC0];
+/// assert_eq!("Duplicate map key: 1 at offset 6", rmpp::unpack_strict_maps(&input).unwrap_err().to_string());
+/// 
+/// let input = vec![0x82, 0xA1, 0x61, 0x01, 0xC4, 0x01, 0x61, 0x02];
+/// assert!(rmpp::unpack_strict_maps(&input).is_ok());
+/// ```
+pub fn unpack_strict_maps(data: &[u8]) -> Result<MsgPackEntry, MsgPackError> {
+    let mut state = ReadState::new(DEFAULT_MAX_DEPTH);
+    state.strict_maps = true;
+    read_slice(data, &mut state)
+}
+
+/// Turns a MessagePack-encoded buffer into a MsgPackEntry object, narrowing every integer to the smallest marker
+/// 
+/// Integers get the markers `pack_minimal` would give them, along with the matching `raw_marker`, 
+///     so messages that differ only in integer widths decode to the same tree. 
+/// Floats and everything else are kept as they are, `unpack` stays faithful to the original markers
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0x92, 0xCF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0xD1, 0xFF, 0x00];
+/// let value = rmpp::unpack_normalized(&input).unwrap();
+/// 
+/// let [a, b] = value.data.as_array().unwrap() else { panic!() };
+/// assert_eq!((0x05, rmpp::MsgPackValue::FixPos(5)), (a.raw_marker, a.data.clone()));
+/// assert_eq!((0xD1, rmpp::MsgPackValue::I16(-256)), (b.raw_marker, b.data.clone()));
+/// 
+/// // Re-encoding gives the same bytes as from a minimal producer
+/// assert_eq!(vec![0x92, 0x05, 0xD1, 0xFF, 0x00], rmpp::pack(&value));
+/// ```
+pub fn unpack_normalized(data: &[u8]) -> Result<MsgPackEntry, MsgPackError> {
+    let mut state = ReadState::new(DEFAULT_MAX_DEPTH);
+    state.normalize_ints = true;
+    read_slice(data, &mut state)
+}
+
+/// Turns a MessagePack-encoded buffer into a MsgPackEntry object, also returning the number of bytes read
+/// 
+/// Useful when multiple values are concatenated inside of a single buffer
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0xC3, 0xC2];
+/// let (value, len) = rmpp::unpack_with_len(&input).unwrap();
+/// 
+/// assert_eq!(rmpp::MsgPackValue::Bool(true), value.data);
+/// assert_eq!(1, len);
+/// ```
+pub fn unpack_with_len(data: &[u8]) -> Result<(MsgPackEntry, usize), MsgPackError> {
+    unpack_at(data, 0)
+}
+
+/// Turns a MessagePack value found at the given offset of a buffer into a MsgPackEntry object, 
+///     also returning the offset right after the value
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0x00, 0x01, 0xC3, 0xC2];
+/// let (value, offset) = rmpp::unpack_at(&input, 2).unwrap();
+/// 
+/// assert_eq!(rmpp::MsgPackValue::Bool(true), value.data);
+/// assert_eq!(3, offset);
+/// ```
+pub fn unpack_at(data: &[u8], offset: usize) -> Result<(MsgPackEntry, usize), MsgPackError> {
+    read_at(data, offset, &mut ReadState::new(DEFAULT_MAX_DEPTH))
+}
+
+/// Turns the first MessagePack value of a buffer into a MsgPackEntry object, also returning the rest of the buffer
+/// 
+/// The rest borrows from the input, which makes decoding concatenated values a loop without any offset arithmetic
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0xC3, 0x92, 0x01, 0x02, 0xA1, 0x61];
+/// let mut values: Vec<rmpp::MsgPackEntry> = vec![];
+/// 
+/// let mut rest: &[u8] = &input;
+/// while !rest.is_empty() {
+///     let (entry, tail) = rmpp::unpack_split(rest).unwrap();
+///     values.push(entry);
+///     rest = tail;
+/// }
+/// 
+/// assert_eq!(rmpp::unpack_all(&input).unwrap(), values);
+/// assert!(core::ptr::eq(&input[1..], rmpp::unpack_split(&input).unwrap().1));
+/// ```
+pub fn unpack_split(data: &[u8]) -> Result<(MsgPackEntry, &[u8]), MsgPackError> {
+    let (entry, len) = unpack_with_len(data)?;
+    Ok((entry, &data[len..]))
+}
+
+/// Turns a MessagePack-encoded buffer into a MsgPackEntry object, failing if the value doesn't span the entire buffer
+/// 
+/// # Examples 
+/// 
+/// ```
+/// assert!(rmpp::unpack_exact(&[0xC3]).is_ok());
+/// assert!(rmpp::unpack_exact(&[0xC3, 0xC2]).is_err());
+/// ```
+pub fn unpack_exact(data: &[u8]) -> Result<MsgPackEntry, MsgPackError> {
+    let (entry, len) = unpack_with_len(data)?;
+    if len != data.len() { return Err(MsgPackError::Custom("trailing bytes".to_string())); }
+    Ok(entry)
+}
+
+/// Turns a buffer of concatenated MessagePack values into a list of MsgPackEntry objects
+/// 
+/// Reads until the buffer is exhausted, failing if the trailing bytes form an incomplete value
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0xC3, 0xC2, 0xC0];
+/// let values = rmpp::unpack_all(&input).unwrap();
+/// 
+/// assert_eq!(3, values.len());
+/// assert!(matches!(rmpp::unpack_all(&[0xC3, 0xCD, 0x01]), Err(rmpp::MsgPackError::Truncated)));
+/// ```
+pub fn unpack_all(data: &[u8]) -> Result<Vec<MsgPackEntry>, MsgPackError> {
+    let mut offset: usize = 0;
+    let mut entries: Vec<MsgPackEntry> = vec![];
+
+    while offset < data.len() {
+        let (entry, next) = read_at(data, offset, &mut ReadState::new(DEFAULT_MAX_DEPTH))?;
+        entries.push(entry);
+        offset = next;
+    }
+
+    Ok(entries)
+}
+
+/// Turns many independent MessagePack-encoded buffers into MsgPackEntry objects in parallel, one result per buffer
+/// 
+/// The buffers are decoded on rayon's global thread pool, the results keep the order of the buffers
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let blobs: Vec<Vec<u8>> = (0..1000u32).map(|n| rmpp::pack(&rmpp::msgpack!([n as u64, "x"]))).chain([vec![0xC1]]).collect();
+/// let values = rmpp::unpack_batch(&blobs);
+/// 
+/// assert_eq!(1001, values.len());
+/// assert_eq!(Some(999), values[999].as_ref().unwrap().data.as_array().unwrap()[0].data.as_u64());
+/// assert!(values[1000].is_err());
+/// ```
+#[cfg(feature = "rayon")]
+pub fn unpack_batch(blobs: &[Vec<u8>]) -> Vec<Result<MsgPackEntry, MsgPackError>> {
+    use rayon::prelude::*;
+
+    blobs.par_iter().map(|blob| unpack(blob)).collect()
+}
+
+/// Reads a MessagePack value from a reader into an existing MsgPackEntry object, 
+///     reusing the buffers of the strings, binary, arrays and maps it held
+/// 
+/// Buffers are handed out in the order the old value held them, so similarly shaped values reuse them the best, 
+///     while a differently shaped value just allocates whatever is missing. The entry is left Null on error
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let mut entry = rmpp::MsgPackEntry::default();
+/// 
+/// for input in [vec![0x92, 0xA2, 0x68, 0x69, 0x01], vec![0x92, 0xA2, 0x79, 0x6F, 0x02], vec![0x81, 0xC3, 0xC2]] {
+///     rmpp::read_value_reuse(&mut &input[..], &mut entry).unwrap();
+///     assert_eq!(rmpp::unpack(&input).unwrap(), entry);
+/// }
+/// ```
+pub fn read_value_reuse<R: Read>(reader: &mut R, entry: &mut MsgPackEntry) -> Result<(), MsgPackError> {
+    let mut state = ReadState::new(DEFAULT_MAX_DEPTH);
+    state.recycle(core::mem::take(&mut entry.data));
+    state.spare_bytes.reverse(); // Popped from the end
+    state.spare_arrays.reverse();
+    state.spare_maps.reverse();
+
+    *entry = MsgPackEntry::default();
+    *entry = read_value(reader, &mut state)?;
+    Ok(())
+}
+
+/// Iterates over the MessagePack values concatenated in a buffer, e.g. a memory-mapped file, without copying it
+/// 
+/// See `MsgPackSliceIter` for the offset tracking
+/// 
 /// # Examples 
 /// 
 /// ```
-/// let input = vec![0xC3];
-/// let value = rmpp::unpack_json(&input, Some(false)).unwrap();
+/// let input = vec![0xC3, 0x92, 0x01, 0x02, 0xA1, 0x61];
+/// let mut values = rmpp::unpack_all_from_slice(&input);
 /// 
-/// assert_eq!(
-///     r###"{"raw_marker":195,"basic_type":"Bool","data":{"type":"Bool","value":true}}"###, 
-///     value
-/// );
+/// assert_eq!(rmpp::MsgPackValue::Bool(true), values.next().unwrap().unwrap().data);
+/// assert_eq!(1, values.offset());
+/// assert_eq!(Some(2), values.next().unwrap().unwrap().data.len());
+/// assert_eq!(4, values.offset());
+/// assert_eq!(1, values.count());
 /// ```
-#[wasm_bindgen]
-pub fn unpack_json(data: &[u8], pretty: Option<bool>) -> Result<String, JsValue> {
-    let value = read_value(&mut Cursor::new(data))
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+pub fn unpack_all_from_slice(data: &[u8]) -> MsgPackSliceIter<'_> {
+    MsgPackSliceIter { data, offset: 0, failed: false }
+}
 
-    if pretty.unwrap_or(false) { serde_json::to_string_pretty(&value) } else { serde_json::to_string(&value) } 
-        .map_err(|e| JsValue::from_str(&e.to_string()))
+/// Iterator over the MessagePack values of a buffer, see `unpack_all_from_slice`
+/// 
+/// Stops after the first error, since the following values can't be located past a broken one
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0xC3, 0xC1, 0xC2];
+/// let mut values = rmpp::unpack_all_from_slice(&input);
+/// 
+/// assert!(values.next().unwrap().is_ok());
+/// assert!(values.next().unwrap().is_err());
+/// assert_eq!(1, values.offset()); // Where the broken value starts
+/// assert!(values.next().is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct MsgPackSliceIter<'a> {
+    data: &'a [u8],
+    offset: usize,
+    failed: bool,
+}
+impl MsgPackSliceIter<'_> {
+    /// Returns the offset of the next value, which is the end of the last one read
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
 }
+impl Iterator for MsgPackSliceIter<'_> {
+    type Item = Result<MsgPackEntry, MsgPackError>;
 
-/// Turns a MessagePack-encoded buffer into a MsgPackEntry object
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.offset >= self.data.len() { return None; }
+
+        match unpack_at(self.data, self.offset) {
+            Ok((entry, offset)) => {
+                self.offset = offset;
+                Some(Ok(entry))
+            },
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Iterates over MessagePack values coming from a reader one at a time
+/// 
+/// Yields None on a clean end of stream and an error if the stream ends mid-value
 /// 
 /// # Examples 
 /// 
 /// ```
-/// let input = vec![0xC3];
-/// let value = rmpp::unpack(&input).unwrap();
+/// let input: &[u8] = &[0xC3, 0xC2];
+/// let values: Vec<_> = rmpp::MsgPackReader::new(input).collect();
 /// 
-/// let entry = rmpp::MsgPackEntry::new(
-///     195, rmpp::MsgPackValue::Bool(true)
-/// );
+/// assert_eq!(2, values.len());
+/// assert_eq!(rmpp::MsgPackValue::Bool(false), values[1].as_ref().unwrap().data);
 /// 
-/// assert_eq!(entry, value);
+/// let input: &[u8] = &[0xC3, 0xCD, 0x01];
+/// let mut reader = rmpp::MsgPackReader::new(input);
+/// 
+/// assert!(reader.next().unwrap().is_ok());
+/// assert!(reader.next().unwrap().is_err());
 /// ```
-pub fn unpack(data: &[u8]) -> Result<MsgPackEntry, MsgPackError> {
-    read_value(&mut Cursor::new(data))
+pub struct MsgPackReader<R: Read> {
+    reader: R,
+}
+impl<R: Read> MsgPackReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// Returns the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+impl<R: Read> Iterator for MsgPackReader<R> {
+    type Item = Result<MsgPackEntry, MsgPackError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Reading the marker separately tells the end of stream apart from a truncated value
+        let mut marker = [0u8; 1];
+        loop {
+            match self.reader.read(&mut marker) {
+                Ok(0) => return None,
+                Ok(_) => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+
+        // Then the marker is put back in front of the rest of the value
+        let mut reader = (&marker[..]).chain(&mut self.reader);
+        Some(read_value(&mut reader, &mut ReadState::new(DEFAULT_MAX_DEPTH)))
+    }
+}
+
+/// Iterates over MessagePack values framed with a 4-byte big-endian length prefix
+/// 
+/// Every frame has to hold exactly one value spanning the whole frame. 
+/// Yields None on a clean end of stream and an error if the stream ends mid-frame
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input: &[u8] = &[0x00, 0x00, 0x00, 0x01, 0xC3, 0x00, 0x00, 0x00, 0x02, 0x91, 0xC0];
+/// let values: Vec<_> = rmpp::FramedReader::new(input).collect();
+/// 
+/// assert_eq!(2, values.len());
+/// assert_eq!(rmpp::MsgPackValue::Bool(true), values[0].as_ref().unwrap().data);
+/// 
+/// // The value has to consume exactly the framed length
+/// let input: &[u8] = &[0x00, 0x00, 0x00, 0x02, 0xC3, 0xC2];
+/// assert!(rmpp::FramedReader::new(input).next().unwrap().is_err());
+/// 
+/// let input: &[u8] = &[0x00, 0x00, 0x00, 0x02, 0xC3];
+/// assert!(matches!(rmpp::FramedReader::new(input).next().unwrap(), Err(rmpp::MsgPackError::Truncated)));
+/// 
+/// let input: &[u8] = &[0x00, 0x00, 0x00, 0x00];
+/// assert_eq!("Empty frame", rmpp::FramedReader::new(input).next().unwrap().unwrap_err().to_string());
+/// ```
+pub struct FramedReader<R: Read> {
+    reader: R,
+}
+impl<R: Read> FramedReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// Returns the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+impl<R: Read> Iterator for FramedReader<R> {
+    type Item = Result<MsgPackEntry, MsgPackError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Reading the first byte separately tells the end of stream apart from a truncated frame
+        let mut prefix = [0u8; 4];
+        loop {
+            match self.reader.read(&mut prefix[..1]) {
+                Ok(0) => return None,
+                Ok(_) => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+
+        let frame = self.reader.read_exact(&mut prefix[1..]).map_err(MsgPackError::from)
+            .and_then(|_| read_bytes(&mut self.reader, len2usize(u32::from_be_bytes(prefix))?));
+        Some(frame.and_then(|frame| {
+            // More bytes can't complete a frame, so it's not reported as truncated
+            if frame.is_empty() { return Err(MsgPackError::Custom("Empty frame".to_string())); }
+
+            let (entry, len) = unpack_with_len(&frame)?;
+            if len != frame.len() {
+                return Err(MsgPackError::Custom(format!("Frame of {} bytes holds a value of {} bytes", frame.len(), len)));
+            }
+            Ok(entry)
+        }))
+    }
+}
+
+/// Callbacks for the events of `decode_events`, every one of them does nothing by default
+/// 
+/// Returning an error from any of them stops the decode with that error
+pub trait MsgPackVisitor {
+    /// Called with every value that isn't an array or a map
+    fn on_scalar(&mut self, _entry: MsgPackEntry) -> Result<(), MsgPackError> { Ok(()) }
+    /// Called before the elements of an array, with its marker and the number of elements
+    fn on_array_start(&mut self, _raw_marker: u8, _len: usize) -> Result<(), MsgPackError> { Ok(()) }
+    /// Called after the last element of an array
+    fn on_array_end(&mut self) -> Result<(), MsgPackError> { Ok(()) }
+    /// Called before the pairs of a map, with its marker and the number of pairs
+    fn on_map_start(&mut self, _raw_marker: u8, _len: usize) -> Result<(), MsgPackError> { Ok(()) }
+    /// Called before the events of every map key
+    fn on_key(&mut self) -> Result<(), MsgPackError> { Ok(()) }
+    /// Called before the events of every map value
+    fn on_value(&mut self) -> Result<(), MsgPackError> { Ok(()) }
+    /// Called after the last pair of a map
+    fn on_map_end(&mut self) -> Result<(), MsgPackError> { Ok(()) }
+}
+
+/// Reads a single MessagePack value out of a reader as a stream of events, without building a tree
+/// 
+/// Arrays and maps are announced by their start and end events with the elements and pairs in between, 
+///     only the scalars are handed out as MsgPackEntry objects. 
+/// The nesting depth is limited to `DEFAULT_MAX_DEPTH` like with `unpack`
+/// 
+/// # Examples 
+/// 
+/// ```
+/// use rmpp::{MsgPackEntry, MsgPackError, MsgPackVisitor};
+/// 
+/// // Sums up every integer under a "n" key
+/// #[derive(Default)]
+/// struct Sum { total: i64, is_key: bool, take_next: bool }
+/// impl MsgPackVisitor for Sum {
+///     fn on_scalar(&mut self, entry: MsgPackEntry) -> Result<(), MsgPackError> {
+///         if self.is_key { self.take_next = entry.data.as_str() == Some("n"); }
+///         else if std::mem::take(&mut self.take_next) { self.total += entry.data.as_i64().unwrap_or(0); }
+///         Ok(())
+///     }
+///     fn on_key(&mut self) -> Result<(), MsgPackError> { self.is_key = true; Ok(()) }
+///     fn on_value(&mut self) -> Result<(), MsgPackError> { self.is_key = false; Ok(()) }
+/// }
+/// 
+/// // [{"n": 1}, {"n": 300, "m": 5}, {"n": -4}]
+/// let input = vec![0x93, 0x81, 0xA1, 0x6E, 0x01, 0x82, 0xA1, 0x6E, 0xCD, 0x01, 0x2C, 0xA1, 0x6D, 0x05, 0x81, 0xA1, 0x6E, 0xFC];
+/// let mut sum = Sum::default();
+/// rmpp::decode_events(&mut &input[..], &mut sum).unwrap();
+/// 
+/// assert_eq!(297, sum.total);
+/// ```
+pub fn decode_events<R: Read, V: MsgPackVisitor + ?Sized>(reader: &mut R, visitor: &mut V) -> Result<(), MsgPackError> {
+    read_events(reader, visitor, &mut ReadState::new(DEFAULT_MAX_DEPTH))
+}
+
+/// Reads a value and hands its events to the visitor, recursing into arrays and maps
+fn read_events<R: Read, V: MsgPackVisitor + ?Sized>(reader: &mut R, visitor: &mut V, state: &mut ReadState) -> Result<(), MsgPackError> {
+    let raw_marker: u8 = reader.read_u8()?;
+    state.node()?;
+
+    let len: usize = match Marker::from_u8(raw_marker) {
+        Marker::FixArray(val)|Marker::FixMap(val) => usize::from(val & 0b0000_1111),
+        Marker::Array16|Marker::Map16 => usize::from(reader.read_u16::<BigEndian>()?),
+        Marker::Array32|Marker::Map32 => read_len32(reader)?,
+        _ => return visitor.on_scalar(read_marked(reader, raw_marker, state)?)
+    };
+
+    state.enter()?;
+    if classify_marker(raw_marker) == Some(BasicTypes::Array) {
+        visitor.on_array_start(raw_marker, len)?;
+        for _ in 0..len { read_events(reader, visitor, state)?; }
+        visitor.on_array_end()?;
+    } else {
+        visitor.on_map_start(raw_marker, len)?;
+        for _ in 0..len {
+            visitor.on_key()?;
+            read_events(reader, visitor, state)?;
+            visitor.on_value()?;
+            read_events(reader, visitor, state)?;
+        }
+        visitor.on_map_end()?;
+    }
+    state.leave();
+
+    Ok(())
+}
+
+/// Advances a reader past exactly one MessagePack value without decoding it, returning the number of bytes skipped
+/// 
+/// Collections are walked by counting the values left to skip, so nothing gets allocated 
+///     no matter how large or deeply nested the value is. Strings aren't checked for valid UTF-8
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0x82, 0xA1, 0x61, 0x92, 0x01, 0xC4, 0x01, 0xFF, 0xA1, 0x62, 0xC0, 0xC3];
+/// let mut reader: &[u8] = &input;
+/// 
+/// assert_eq!(11, rmpp::skip_value(&mut reader).unwrap());
+/// assert_eq!(rmpp::MsgPackValue::Bool(true), rmpp::unpack_from(&mut reader).unwrap().data);
+/// 
+/// assert!(matches!(rmpp::skip_value(&mut &[0x92, 0xC0][..]), Err(rmpp::MsgPackError::Truncated)));
+/// assert_eq!(Some(3), rmpp::skip_value(&mut &[0x92, 0xC0, 0xC1][..]).unwrap_err().offset());
+/// ```
+pub fn skip_value<R: Read>(reader: &mut R) -> Result<usize, MsgPackError> {
+    let mut reader = CountingReader { reader, count: 0 };
+    let mut remaining: u64 = 1; // Values left to skip, collections add their elements
+
+    while remaining > 0 {
+        let (_, children) = skip_header(&mut reader).map_err(|e| at_offset(reader.count, e))?;
+        remaining = remaining - 1 + children;
+    }
+
+    Ok(reader.count)
+}
+
+/// Counts the bytes read through it, so that errors can tell where they happened
+struct CountingReader<R: Read> {
+    reader: R,
+    count: usize,
+}
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n: usize = self.reader.read(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+}
+
+/// Skips the marker, the length and the data of a single value, returning the number of bytes skipped 
+///     along with the number of values the collection holds (or 0 for anything else)
+fn skip_header<R: Read>(reader: &mut R) -> Result<(usize, u64), MsgPackError> {
+    let (header_len, payload_len, children) = read_header(reader)?;
+    Ok((header_len + skip_bytes(reader, payload_len)?, children))
+}
+
+/// Reads the marker and the length of a single value, returning the number of bytes read, 
+///     the length of the data that follows and the number of values the collection holds (or 0 for anything else)
+fn read_header<R: Read>(reader: &mut R) -> Result<(usize, usize, u64), MsgPackError> {
+    let marker: Marker = Marker::from_u8(reader.read_u8()?);
+    let mut children: u64 = 0;
+
+    // Length of the length field and of the data that follows it, or of the element count
+    let (len_size, data_len): (usize, usize) = match marker {
+        Marker::Null|Marker::False|Marker::True|Marker::FixPos(_)|Marker::FixNeg(_) => (0, 0),
+        Marker::U8|Marker::I8 => (0, 1),
+        Marker::U16|Marker::I16 => (0, 2),
+        Marker::U32|Marker::I32|Marker::F32 => (0, 4),
+        Marker::U64|Marker::I64|Marker::F64 => (0, 8),
+        Marker::FixStr(val) => (0, usize::from(val & 0b0001_1111)),
+        Marker::Str8|Marker::Bin8 => (1, 0),
+        Marker::Str16|Marker::Bin16 => (2, 0),
+        Marker::Str32|Marker::Bin32 => (4, 0),
+        // The extension type takes one more byte
+        Marker::FixExt1 => (0, 2),
+        Marker::FixExt2 => (0, 3),
+        Marker::FixExt4 => (0, 5),
+        Marker::FixExt8 => (0, 9),
+        Marker::FixExt16 => (0, 17),
+        Marker::Ext8 => (1, 1),
+        Marker::Ext16 => (2, 1),
+        Marker::Ext32 => (4, 1),
+        Marker::FixArray(val) => { children = u64::from(val & 0b0000_1111); (0, 0) },
+        Marker::FixMap(val) => { children = u64::from(val & 0b0000_1111) * 2; (0, 0) },
+        Marker::Array16|Marker::Map16 => (2, 0),
+        Marker::Array32|Marker::Map32 => (4, 0),
+        Marker::Reserved => return Err(MsgPackError::Custom("Reserved marker 0xC1".to_string()))
+    };
+
+    let len: usize = match len_size {
+        1 => reader.read_u8()? as usize,
+        2 => reader.read_u16::<BigEndian>()? as usize,
+        4 => read_len32(reader)?,
+        _ => 0
+    };
+    let payload_len: usize = match marker {
+        Marker::Array16|Marker::Array32 => { children = len as u64; 0 },
+        Marker::Map16|Marker::Map32 => { children = len as u64 * 2; 0 },
+        _ => len.checked_add(data_len).ok_or_else(|| len_error(len))?
+    };
+
+    Ok((1 + len_size, payload_len, children))
+}
+
+/// Reads and discards exactly `len` bytes through a small buffer
+fn skip_bytes<R: Read>(reader: &mut R, mut len: usize) -> Result<usize, MsgPackError> {
+    let total: usize = len;
+    let mut buf = [0u8; 256];
+    while len > 0 {
+        let chunk: usize = len.min(buf.len());
+        reader.read_exact(&mut buf[..chunk])?;
+        len -= chunk;
+    }
+    Ok(total)
+}
+
+/// Decodes values out of bytes arriving in chunks, e.g. from a non-blocking socket
+/// 
+/// `poll` returns `Ok(None)` until a complete value is buffered. 
+/// The value boundary is found by scanning the headers once as the bytes arrive, 
+///     so the value itself is only decoded when it's complete.
+/// Errors carry the offset into the buffered value. After an error the buffered data can't be trusted and the decoder should be dropped
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let mut decoder = rmpp::Decoder::new();
+/// 
+/// decoder.push(&[0x92, 0xA2, 0x68]);
+/// assert_eq!(None, decoder.poll().unwrap());
+/// 
+/// decoder.push(&[0x69, 0xC3, 0xC0]);
+/// assert_eq!(rmpp::msgpack!(["hi", true]), decoder.poll().unwrap().unwrap());
+/// assert_eq!(rmpp::msgpack!(null), decoder.poll().unwrap().unwrap());
+/// assert_eq!(None, decoder.poll().unwrap());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Decoder {
+    buf: Vec<u8>,
+    /// Offset of the first value header not scanned yet
+    scanned: usize,
+    /// Values left to scan before the current value is complete
+    remaining: u64,
+    /// Bytes of the last scanned header's data that haven't arrived yet
+    pending_payload: usize,
+}
+impl Default for Decoder {
+    fn default() -> Self {
+        Self { buf: vec![], scanned: 0, remaining: 1, pending_payload: 0 }
+    }
+}
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends bytes to the internal buffer
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Returns the next value if it's completely buffered, or None if more bytes are needed
+    pub fn poll(&mut self) -> Result<Option<MsgPackEntry>, MsgPackError> {
+        while self.remaining > 0 || self.pending_payload > 0 {
+            // Data is skipped over as it arrives rather than rescanned on every poll
+            if self.pending_payload > 0 {
+                let arrived: usize = (self.buf.len() - self.scanned).min(self.pending_payload);
+                self.scanned += arrived;
+                self.pending_payload -= arrived;
+                if self.pending_payload > 0 { return Ok(None); }
+                continue;
+            }
+
+            let mut header: &[u8] = &self.buf[self.scanned..];
+            match read_header(&mut header) {
+                Ok((len, payload_len, children)) => {
+                    self.scanned += len;
+                    self.pending_payload = payload_len;
+                    self.remaining = self.remaining - 1 + children;
+                },
+                Err(MsgPackError::Truncated) => return Ok(None), // Only the header gets scanned again once more bytes arrive
+                Err(e) => return Err(at_offset(self.buf.len() - header.len(), e))
+            }
+        }
+
+        let (entry, _) = unpack_with_len(&self.buf[..self.scanned])?;
+        self.buf.drain(..self.scanned);
+        self.scanned = 0;
+        self.remaining = 1;
+        Ok(Some(entry))
+    }
+}
+
+/// Tells the basic type of a value from its marker byte alone, without reading the rest of the value
+/// 
+/// Returns None for 0xC1, the one marker MessagePack never uses
+/// 
+/// # Examples 
+/// 
+/// ```
+/// use rmpp::{classify_marker, BasicTypes};
+/// 
+/// assert_eq!(Some(BasicTypes::Map), classify_marker(0x82));
+/// assert_eq!(Some(BasicTypes::Number), classify_marker(0xCB));
+/// assert_eq!(Some(BasicTypes::Ext), classify_marker(0xD6));
+/// assert_eq!(None, classify_marker(0xC1));
+/// ```
+pub fn classify_marker(byte: u8) -> Option<BasicTypes> {
+    let res: BasicTypes = match Marker::from_u8(byte) {
+        // Null
+        Marker::Null => BasicTypes::Null,
+        // Boolean
+        Marker::False|Marker::True => BasicTypes::Bool,
+        // Integer + Float
+        Marker::FixPos(_)|Marker::FixNeg(_)|
+        Marker::U8|Marker::U16|Marker::U32|Marker::U64|
+        Marker::I8|Marker::I16|Marker::I32|Marker::I64|
+        Marker::F32|Marker::F64 => BasicTypes::Number,
+        // String
+        Marker::FixStr(_)|Marker::Str8|Marker::Str16|Marker::Str32 => BasicTypes::String,
+        // Binary
+        Marker::Bin8|Marker::Bin16|Marker::Bin32 => BasicTypes::Bin,
+        // Array
+        Marker::FixArray(_)|Marker::Array16|Marker::Array32 => BasicTypes::Array,
+        // Map
+        Marker::FixMap(_)|Marker::Map16|Marker::Map32 => BasicTypes::Map,
+        // Extension
+        Marker::Ext8|Marker::Ext16|Marker::Ext32|
+        Marker::FixExt1|Marker::FixExt2|Marker::FixExt4|Marker::FixExt8|Marker::FixExt16 => BasicTypes::Ext,
+        Marker::Reserved => return None
+    };
+
+    Some(res)
+}
+
+/// Reads a MessagePack value out of a buffer, see `read_at`
+fn read_slice(data: &[u8], state: &mut ReadState) -> Result<MsgPackEntry, MsgPackError> {
+    read_at(data, 0, state).map(|(entry, _)| entry)
+}
+
+/// Reads a MessagePack value found at the given offset of a buffer, also returning the offset right after it
+/// 
+/// Errors are wrapped into `MsgPackError::At` with the offset decoding stopped at. 
+/// Truncated data is left as is, it always stops at the end of the buffer and callers match on it to wait for more bytes
+fn read_at(data: &[u8], offset: usize, state: &mut ReadState) -> Result<(MsgPackEntry, usize), MsgPackError> {
+    let mut cursor = Cursor::new(data);
+    cursor.set_position(offset as u64);
+
+    match read_value(&mut cursor, state) {
+        Ok(entry) => Ok((entry, cursor.position() as usize)),
+        Err(e) => Err(at_offset(cursor.position() as usize, e))
+    }
+}
+
+/// Wraps an error into `MsgPackError::At` with the offset decoding stopped at, leaving `Truncated` as is
+pub(crate) fn at_offset(offset: usize, e: MsgPackError) -> MsgPackError {
+    match e {
+        MsgPackError::Truncated => e,
+        e => MsgPackError::At { offset, source: Box::new(e) }
+    }
 }
 
 /// Reads a MessagePack buffer value and returns a MsgPackEntry object
 /// 
 /// If a value is of collection type (e.g. Array or Map), it'll read the entire collection
-fn read_value<R: Read>(reader: &mut R) -> Result<MsgPackEntry, MsgPackError> {
+fn read_value<R: Read>(reader: &mut R, state: &mut ReadState) -> Result<MsgPackEntry, MsgPackError> {
     // Read the marker
     let raw_marker: u8 = reader.read_u8()?;
+    state.node()?;
+
+    read_marked(reader, raw_marker, state)
+}
+
+/// Reads the rest of a value whose marker has already been read
+fn read_marked<R: Read>(reader: &mut R, raw_marker: u8, state: &mut ReadState) -> Result<MsgPackEntry, MsgPackError> {
     let marker: Marker = Marker::from_u8(raw_marker);
 
     // Read the value
@@ -79,43 +1401,93 @@ fn read_value<R: Read>(reader: &mut R) -> Result<MsgPackEntry, MsgPackError> {
         Marker::F32 => { MsgPackValue::F32(reader.read_f32::<BigEndian>()?) }
         Marker::F64 => { MsgPackValue::F64(reader.read_f64::<BigEndian>()?) }
         // String
-        Marker::FixStr(_)|Marker::Str8|Marker::Str16|Marker::Str32 => { read_str(reader, marker)? },
+        Marker::FixStr(_)|Marker::Str8|Marker::Str16|Marker::Str32 => { read_str(reader, marker, state)? },
         // Binary
-        Marker::Bin8|Marker::Bin16|Marker::Bin32 => { read_bin(reader, marker)? },
+        Marker::Bin8|Marker::Bin16|Marker::Bin32 => { read_bin(reader, marker, state)? },
         // Array
-        Marker::FixArray(_)|Marker::Array16|Marker::Array32 => { read_array(reader, marker)? },
+        Marker::FixArray(_)|Marker::Array16|Marker::Array32 => { read_array(reader, marker, state)? },
         // Map
-        Marker::FixMap(_)|Marker::Map16|Marker::Map32 => { read_map(reader, marker)? },
-        // Extension - I don't really care about it, teehee
+        Marker::FixMap(_)|Marker::Map16|Marker::Map32 => { read_map(reader, marker, state)? },
+        // Extension
         Marker::Ext8|Marker::Ext16|Marker::Ext32|
-        Marker::FixExt1|Marker::FixExt2|Marker::FixExt4|Marker::FixExt8|Marker::FixExt16 => {
-            unimplemented!()
-        },
-        Marker::Reserved => {
-            unreachable!()
-        }
+        Marker::FixExt1|Marker::FixExt2|Marker::FixExt4|Marker::FixExt8|Marker::FixExt16 => { read_ext(reader, marker, state)? },
+        Marker::Reserved => return Err(MsgPackError::Custom("Reserved marker 0xC1".to_string())) // Never valid, corrupt input
     };
 
+    // Over-wide integers take the narrowest marker and the matching raw marker
+    if state.normalize_ints && let Some(n) = value.as_int() {
+        let value: MsgPackValue = match u64::try_from(n) {
+            Ok(n) => uint2value(n),
+            Err(_) => int2value(n as i64) // Anything that isn't u64 fits i64
+        };
+        return Ok(MsgPackEntry::from(value));
+    }
+
     Ok(MsgPackEntry::new(raw_marker, value))
 }
 
 
+/// Reads exactly `len` bytes
+/// 
+/// The declared length can't be trusted, so the buffer grows as the data actually arrives 
+///     instead of being allocated upfront
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>, MsgPackError> {
+    read_bytes_into(reader, len, vec![])
+}
+
+/// Reads exactly `len` bytes into an emptied buffer, reusing its capacity
+fn read_bytes_into<R: Read>(reader: &mut R, len: usize, mut buf: Vec<u8>) -> Result<Vec<u8>, MsgPackError> {
+    buf.clear();
+    buf.reserve(len.min(MAX_PREALLOC));
+    reader.by_ref().take(len as u64).read_to_end(&mut buf)?;
+
+    // A shorter read means the buffer ended before the declared length
+    if buf.len() != len { return Err(MsgPackError::Truncated); }
+
+    Ok(buf)
+}
+
+/// Reads a 32-bit length field, see `len2usize`
+fn read_len32<R: Read>(reader: &mut R) -> Result<usize, MsgPackError> {
+    len2usize(reader.read_u32::<BigEndian>()?)
+}
+
+/// Converts a declared 32-bit length to usize, failing on targets where it doesn't fit
+/// 
+/// Lengths always fit on 32-bit targets like wasm32, yet the conversion is checked rather than trusted
+pub(crate) fn len2usize(len: u32) -> Result<usize, MsgPackError> {
+    usize::try_from(len).map_err(|_| len_error(len))
+}
+
+/// Error of a declared length the platform can't address
+fn len_error(len: impl core::fmt::Display) -> MsgPackError {
+    MsgPackError::Custom(format!("Declared length {} doesn't fit the address space", len))
+}
+
 /// Reads MessagePack strings
-fn read_str<R: Read>(reader: &mut R, marker: Marker) -> Result<MsgPackValue, MsgPackError> {
+fn read_str<R: Read>(reader: &mut R, marker: Marker, state: &mut ReadState) -> Result<MsgPackValue, MsgPackError> {
     let len: usize = match marker {
         // FixStr has the length from 0 to 31 encoded inside of it
         Marker::FixStr(val) => { usize::from(val & 0b0001_1111) } // Lower 5 bits represent the length
         // Otherwise, the first 1/2/4 byte(s) after the marker represent the length
         Marker::Str8  => { reader.read_u8()? as usize },
         Marker::Str16 => { reader.read_u16::<BigEndian>()? as usize },
-        Marker::Str32 => { reader.read_u32::<BigEndian>()? as usize },
+        Marker::Str32 => { read_len32(reader)? },
         _ => unreachable!()
     };
 
     // After that comes the string data
-    let mut buf: Vec<u8> = vec![0u8;len];
-    reader.read_exact(&mut buf)?;
-    let s=String::from_utf8(buf).map_err(|e| MsgPackError::Custom(format!("Invalid UTF-8: {}", e)))?;
+    state.alloc(len)?;
+    let buf: Vec<u8> = read_bytes_into(reader, len, state.spare_bytes.pop().unwrap_or_default())?;
+    let s: String = match String::from_utf8(buf) {
+        Ok(s) => s,
+        Err(e) => match state.utf8 {
+            Utf8Policy::Strict => return Err(MsgPackError::Custom(format!("Invalid UTF-8: {}", e))),
+            Utf8Policy::Lossy => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+            Utf8Policy::Raw => return Ok(MsgPackValue::StrRaw(e.into_bytes())),
+            Utf8Policy::Custom(repair) => repair(e.as_bytes())?,
+        }
+    };
 
     let res: MsgPackValue = match marker {
         Marker::FixStr(_) => { MsgPackValue::FixStr(s) }
@@ -129,18 +1501,18 @@ fn read_str<R: Read>(reader: &mut R, marker: Marker) -> Result<MsgPackValue, Msg
 }
 
 /// Reads MessagePack binary
-fn read_bin<R: Read>(reader: &mut R, marker: Marker) -> Result<MsgPackValue, MsgPackError> {
+fn read_bin<R: Read>(reader: &mut R, marker: Marker, state: &mut ReadState) -> Result<MsgPackValue, MsgPackError> {
     let len: usize = match marker {
         // The first 1/2/4 byte(s) after the marker represent the length
         Marker::Bin8  => { reader.read_u8()? as usize }
         Marker::Bin16 => { reader.read_u16::<BigEndian>()? as usize }
-        Marker::Bin32 => { reader.read_u32::<BigEndian>()? as usize }
+        Marker::Bin32 => { read_len32(reader)? }
         _ => unreachable!()
     };
     
     // After that comes the binary data
-    let mut buf: Vec<u8> = vec![0u8;len];
-    reader.read_exact(&mut buf)?;
+    state.alloc(len)?;
+    let buf: Vec<u8> = read_bytes_into(reader, len, state.spare_bytes.pop().unwrap_or_default())?;
 
     let res: MsgPackValue = match marker {
         Marker::Bin8  => { MsgPackValue::Bin8(buf)  }
@@ -153,19 +1525,23 @@ fn read_bin<R: Read>(reader: &mut R, marker: Marker) -> Result<MsgPackValue, Msg
 }
 
 /// Reads MessagePack arrays
-fn read_array<R: Read>(reader: &mut R, marker: Marker) -> Result<MsgPackValue, MsgPackError> {
+fn read_array<R: Read>(reader: &mut R, marker: Marker, state: &mut ReadState) -> Result<MsgPackValue, MsgPackError> {
     let len: usize = match marker {
         // FixArray has the length from 0 to 15 encoded inside of it
         Marker::FixArray(val) => { usize::from(val & 0b0000_1111) }, // Lower 4 bits represent the length
         // Otherwise, the first 2/4 bytes after the marker represent the length
         Marker::Array16 => { reader.read_u16::<BigEndian>()? as usize },
-        Marker::Array32 => { reader.read_u32::<BigEndian>()? as usize },
+        Marker::Array32 => { read_len32(reader)? },
         _ => unreachable!()
     };
 
     // After that comes the array data
-    let mut array: Vec<MsgPackEntry> = Vec::with_capacity(len);
-    for _ in 0..len { array.push(read_value(reader)?); } // Recursively read each element
+    state.alloc(len.saturating_mul(size_of::<MsgPackEntry>()))?;
+    state.enter()?;
+    let mut array: Vec<MsgPackEntry> = state.spare_arrays.pop().unwrap_or_default();
+    array.reserve(len.min(MAX_PREALLOC)); // The declared length can't be trusted
+    for _ in 0..len { array.push(read_value(reader, state)?); } // Recursively read each element
+    state.leave();
 
     let res: MsgPackValue = match marker {
         Marker::FixArray(_) => { MsgPackValue::FixArray(array) },
@@ -178,24 +1554,32 @@ fn read_array<R: Read>(reader: &mut R, marker: Marker) -> Result<MsgPackValue, M
 }
 
 /// Reads MessagePack maps
-fn read_map<R: Read>(reader: &mut R, marker: Marker) -> Result<MsgPackValue, MsgPackError> {
+fn read_map<R: Read>(reader: &mut R, marker: Marker, state: &mut ReadState) -> Result<MsgPackValue, MsgPackError> {
     let len: usize = match marker {
         // FixMap has the length from 0 to 15 encoded inside of it
         Marker::FixMap(val) => { usize::from(val & 0b0000_1111) }, // Lower 4 bits represent the length
         // Otherwise, the first 2/4 bytes after the marker represent the length
         Marker::Map16 => { reader.read_u16::<BigEndian>()? as usize },
-        Marker::Map32 => { reader.read_u32::<BigEndian>()? as usize },
+        Marker::Map32 => { read_len32(reader)? },
         _ => unreachable!()
     };
 
     // After that comes the map data
-    let mut map: Vec<_> = Vec::with_capacity(len);
+    state.alloc(len.saturating_mul(size_of::<(MsgPackEntry, MsgPackEntry)>()))?;
+    state.enter()?;
+    let mut map: Vec<_> = state.spare_maps.pop().unwrap_or_default();
+    map.reserve(len.min(MAX_PREALLOC)); // The declared length can't be trusted
     for _ in 0..len { 
         // Recursively read each element
-        let k: MsgPackEntry = read_value(reader)?; 
-        let v: MsgPackEntry = read_value(reader)?;
+        let k: MsgPackEntry = read_value(reader, state)?; 
+        let v: MsgPackEntry = read_value(reader, state)?;
         map.push((k, v));
     }
+    state.leave();
+
+    if state.strict_maps && let Some(key) = find_duplicate_key(&map) {
+        return Err(MsgPackError::Custom(format!("Duplicate map key: {}", key)));
+    }
 
     let res: MsgPackValue = match marker {
         Marker::FixMap(_) => { MsgPackValue::FixMap(map) },
@@ -205,4 +1589,264 @@ fn read_map<R: Read>(reader: &mut R, marker: Marker) -> Result<MsgPackValue, Msg
     };
     
     Ok(res)
+}
+
+/// Returns the first key found to be logically equal to another key of the map
+/// 
+/// String and integer keys are sorted to find duplicates, any other keys are compared one by one
+fn find_duplicate_key(map: &[(MsgPackEntry, MsgPackEntry)]) -> Option<&MsgPackValue> {
+    let mut strs: Vec<(&[u8], &MsgPackValue)> = vec![];
+    let mut ints: Vec<(i128, &MsgPackValue)> = vec![];
+    let mut others: Vec<&MsgPackValue> = vec![];
+    for (k, _) in map {
+        if let Some(b) = k.data.str_bytes() { strs.push((b, &k.data)); }
+        else if let Some(n) = k.data.as_int() { ints.push((n, &k.data)); }
+        else { others.push(&k.data); }
+    }
+
+    strs.sort_unstable_by_key(|(b, _)| *b);
+    ints.sort_unstable_by_key(|(n, _)| *n);
+
+    strs.windows(2).find(|w| w[0].0 == w[1].0).map(|w| w[1].1)
+        .or_else(|| ints.windows(2).find(|w| w[0].0 == w[1].0).map(|w| w[1].1))
+        .or_else(|| others.iter().enumerate().find(|(i, k)| others[..*i].iter().any(|o| o.logically_eq(k))).map(|(_, k)| *k))
+}
+
+/// Reads MessagePack extensions
+fn read_ext<R: Read>(reader: &mut R, marker: Marker, state: &mut ReadState) -> Result<MsgPackValue, MsgPackError> {
+    let len: usize = match marker {
+        // FixExt has the length of 1/2/4/8/16 encoded inside of the marker
+        Marker::FixExt1  => 1,
+        Marker::FixExt2  => 2,
+        Marker::FixExt4  => 4,
+        Marker::FixExt8  => 8,
+        Marker::FixExt16 => 16,
+        // Otherwise, the first 1/2/4 byte(s) after the marker represent the length
+        Marker::Ext8  => { reader.read_u8()? as usize },
+        Marker::Ext16 => { reader.read_u16::<BigEndian>()? as usize },
+        Marker::Ext32 => { read_len32(reader)? },
+        _ => unreachable!()
+    };
+
+    // Then comes the signed extension type
+    let ext_type: i8 = reader.read_i8()?;
+
+    // After that comes the extension data
+    state.alloc(len)?;
+    let buf: Vec<u8> = read_bytes_into(reader, len, state.spare_bytes.pop().unwrap_or_default())?;
+
+    // Extension type -1 is reserved for timestamps
+    if ext_type == -1 && let Some(res) = read_timestamp(&buf) { return Ok(res); }
+
+    Ok(MsgPackValue::Ext(ext_type, buf))
+}
+
+/// Reads a MessagePack timestamp out of the extension data
+/// 
+/// Returns None if the data doesn't match any of the timestamp layouts, 
+///     in which case it's better kept as a raw extension
+pub(crate) fn read_timestamp(buf: &[u8]) -> Option<MsgPackValue> {
+    let mut reader = Cursor::new(buf);
+
+    let (seconds, nanos): (i64, u32) = match buf.len() {
+        // Timestamp 32 - unsigned 32-bit seconds
+        4 => { (i64::from(reader.read_u32::<BigEndian>().ok()?), 0) },
+        // Timestamp 64 - upper 30 bits represent the nanoseconds and lower 34 bits represent the seconds
+        8 => {
+            let val: u64 = reader.read_u64::<BigEndian>().ok()?;
+            ((val & 0x0000_0003_FFFF_FFFF) as i64, (val >> 34) as u32)
+        },
+        // Timestamp 96 - unsigned 32-bit nanoseconds followed by signed 64-bit seconds
+        12 => {
+            let nanos: u32 = reader.read_u32::<BigEndian>().ok()?;
+            (reader.read_i64::<BigEndian>().ok()?, nanos)
+        },
+        _ => return None
+    };
+
+    if nanos > 999_999_999 { return None; }
+
+    Some(MsgPackValue::Timestamp { seconds, nanos })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::{pack, pack_json};
+
+    #[test]
+    fn fixneg_json_round_trip() {
+        // Every FixNeg keeps its sign through json and packs back to the very same byte
+        for n in -32i8..=-1 {
+            let input = vec![n as u8];
+            let json = unpack_json(&input, Some(false)).unwrap();
+
+            assert!(json.contains(&format!(r###"{{"type":"FixNeg","value":{}}}"###, n)));
+            assert_eq!(input, pack_json(&json).unwrap());
+            assert_eq!(input, pack(&unpack(&input).unwrap()));
+        }
+    }
+    #[test]
+    fn float_bits_json_round_trip() {
+        let cases: [&[u8]; 4] = [
+            // Negative zero keeps its sign bit
+            &[0xCB, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], &[0xCA, 0x80, 0x00, 0x00, 0x00],
+            // NaN goes through the string sentinel and comes back as the same quiet NaN
+            &[0xCB, 0x7F, 0xF8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], &[0xCA, 0x7F, 0xC0, 0x00, 0x00],
+        ];
+        for input in cases {
+            let json = unpack_json(input, Some(false)).unwrap();
+
+            assert_eq!(input, pack_json(&json).unwrap());
+            assert_eq!(input, pack(&unpack(input).unwrap()));
+        }
+
+        let json = unpack_json(cases[0], Some(false)).unwrap();
+        assert!(json.contains(r###""value":-0.0"###));
+    }
+    #[test]
+    fn integer_markers_json_round_trip() {
+        // Every integer marker survives json as itself, even when a narrower one would hold the value
+        let cases: [(&str, &[u8]); 24] = [
+            ("FixPos", &[0x00]), ("FixPos", &[0x7F]), ("FixNeg", &[0xE0]), ("FixNeg", &[0xFF]),
+            ("U8", &[0xCC, 0x05]), ("U8", &[0xCC, 0xFF]),
+            ("U16", &[0xCD, 0x00, 0x05]), ("U16", &[0xCD, 0xFF, 0xFF]),
+            ("U32", &[0xCE, 0x00, 0x00, 0x00, 0x05]), ("U32", &[0xCE, 0xFF, 0xFF, 0xFF, 0xFF]),
+            ("U64", &[0xCF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05]),
+            ("U64", &[0xCF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+            ("I8", &[0xD0, 0x05]), ("I8", &[0xD0, 0x80]), ("I8", &[0xD0, 0x7F]),
+            ("I16", &[0xD1, 0x00, 0x05]), ("I16", &[0xD1, 0x80, 0x00]),
+            ("I32", &[0xD2, 0xFF, 0xFF, 0xFF, 0xFF]), ("I32", &[0xD2, 0x80, 0x00, 0x00, 0x00]),
+            ("I64", &[0xD3, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05]),
+            ("I64", &[0xD3, 0x7F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+            ("I64", &[0xD3, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+            ("I64", &[0xD3, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+            ("I8", &[0xD0, 0xFF]),
+        ];
+        for (marker, input) in cases {
+            let json = unpack_json(input, Some(false)).unwrap();
+
+            assert!(json.contains(&format!(r###"{{"type":"{}","###, marker)), "{}", json);
+            assert_eq!(input, pack_json(&json).unwrap());
+            assert_eq!(marker, unpack(&pack_json(&json).unwrap()).unwrap().data.marker_name());
+        }
+    }
+    #[test]
+    fn fixint_boundaries() {
+        // Single bytes at the edges of the fixint ranges, 0x80 being the empty FixMap between them
+        let cases = [
+            (0x00, MsgPackValue::FixPos(0), BasicTypes::Number), (0x7F, MsgPackValue::FixPos(127), BasicTypes::Number),
+            (0x80, MsgPackValue::FixMap(vec![]), BasicTypes::Map), (0x90, MsgPackValue::FixArray(vec![]), BasicTypes::Array),
+            (0xE0, MsgPackValue::FixNeg(-32), BasicTypes::Number), (0xFF, MsgPackValue::FixNeg(-1), BasicTypes::Number),
+        ];
+        for (byte, data, basic_type) in cases {
+            let value = unpack(&[byte]).unwrap();
+
+            assert_eq!(MsgPackEntry { raw_marker: byte, basic_type, data }, value);
+            assert_eq!(vec![byte], pack(&value));
+        }
+    }
+    #[test]
+    fn empty_input_is_truncated() {
+        assert!(matches!(unpack(&[]), Err(MsgPackError::Truncated)));
+        assert_eq!("Unexpected end of data", unpack(&[]).unwrap_err().to_string());
+    }
+    #[test]
+    fn decoder_polls_chunked_payloads() {
+        // [Bin32 of 4096 bytes, FixExt1], cut so that chunks end inside headers and inside the data alike
+        let value = MsgPackEntry::from(MsgPackValue::FixArray(vec![
+            MsgPackEntry::from(MsgPackValue::Bin32(vec![0xAB; 0x1000])),
+            MsgPackEntry::from(MsgPackValue::Ext(1, vec![0xFF])),
+        ]));
+        let input: Vec<u8> = pack(&value);
+
+        let mut decoder = Decoder::new();
+        let chunks: Vec<&[u8]> = input.chunks(3).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            decoder.push(chunk);
+            let polled = decoder.poll().unwrap();
+            if i + 1 < chunks.len() {
+                assert_eq!(None, polled, "{}", i);
+            } else {
+                assert_eq!(Some(value.clone()), polled);
+            }
+        }
+        assert_eq!(None, decoder.poll().unwrap());
+
+        // Values that arrive together come out one per poll
+        decoder.push(&[0xC3, 0xC2]);
+        assert_eq!(Some(MsgPackValue::Bool(true)), decoder.poll().unwrap().map(|entry| entry.data));
+        assert_eq!(Some(MsgPackValue::Bool(false)), decoder.poll().unwrap().map(|entry| entry.data));
+        assert_eq!(None, decoder.poll().unwrap());
+    }
+
+    #[test]
+    fn header_errors_carry_offsets() {
+        // The reserved marker after a null and right at the start of an array
+        for (input, offset) in [(&[0x92, 0xC0, 0xC1][..], 3), (&[0x91, 0xC1][..], 2)] {
+            assert_eq!(Some(offset), skip_value(&mut &input[..]).unwrap_err().offset());
+            assert_eq!(Some(offset), unpack(input).unwrap_err().offset());
+
+            let mut decoder = Decoder::new();
+            decoder.push(input);
+            assert_eq!(Some(offset), decoder.poll().unwrap_err().offset());
+        }
+    }
+
+    /// Hands out a single byte per read, with a pending poll in between
+    #[cfg(feature = "tokio")]
+    struct Trickle<'a> {
+        data: &'a [u8],
+        ready: bool,
+    }
+    #[cfg(feature = "tokio")]
+    impl tokio::io::AsyncRead for Trickle<'_> {
+        fn poll_read(
+            mut self: core::pin::Pin<&mut Self>, cx: &mut core::task::Context<'_>, buf: &mut tokio::io::ReadBuf<'_>
+        ) -> core::task::Poll<io::Result<()>> {
+            self.ready = !self.ready;
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return core::task::Poll::Pending;
+            }
+
+            if let Some((&byte, rest)) = self.data.split_first() {
+                buf.put_slice(&[byte]);
+                self.data = rest;
+            }
+            core::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn unpack_async_partial_reads() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+        // [{"a": Bin8}, FixExt2, U16] followed by true
+        let value: &[u8] = &[
+            0x93, 0x81, 0xA1, 0x61, 0xC4, 0x03, 0x01, 0x02, 0x03, 0xD5, 0x05, 0xAA, 0xBB, 0xCD, 0x01, 0x2C,
+        ];
+        let input: Vec<u8> = [value, &[0xC3]].concat();
+        let mut reader = Trickle { data: &input, ready: false };
+
+        let entry = runtime.block_on(unpack_async(&mut reader)).unwrap();
+        assert_eq!(unpack(value).unwrap(), entry);
+        assert_eq!(value, pack(&entry));
+        assert_eq!(MsgPackValue::Bool(true), runtime.block_on(unpack_async(&mut reader)).unwrap().data);
+
+        // Cut off anywhere, be it in a header or in the data
+        for len in 0..value.len() {
+            let mut reader = Trickle { data: &value[..len], ready: false };
+            assert!(matches!(runtime.block_on(unpack_async(&mut reader)), Err(MsgPackError::Truncated)), "{}", len);
+        }
+
+        // A Str32 claiming 4GB doesn't allocate 4GB
+        let mut reader = Trickle { data: &[0xDB, 0xFF, 0xFF, 0xFF, 0xFF, 0x61], ready: false };
+        assert!(matches!(runtime.block_on(unpack_async(&mut reader)), Err(MsgPackError::Truncated)));
+
+        let mut reader = Trickle { data: &[0x91, 0xC1], ready: false };
+        assert_eq!(Some(2), runtime.block_on(unpack_async(&mut reader)).unwrap_err().offset());
+    }
 }
\ No newline at end of file
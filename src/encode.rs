@@ -1,6 +1,13 @@
-use std::io::Write;
+use crate::prelude::*;
+use crate::io::{self, Write};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
-use crate::types::{MsgValue, MsgPackEntry, MsgPackValue};
+use crate::types::{MsgPackError, MsgValue, MsgPackEntry, MsgPackValue, JsonError, json_error};
+use crate::types::{value2marker, uint2value, int2value, str2value, bin2value, array2value, map2value};
+use crate::decode::{unpack, classify_marker};
+use crate::json::validate_entry;
+use crate::markers;
+use rmp::Marker;
 
 
 /// Turns a json-encoded MsgPackEntry string into a MessagePack-encoded buffer
@@ -19,19 +26,151 @@ use crate::types::{MsgValue, MsgPackEntry, MsgPackValue};
 /// }
 /// "###;
 /// 
-/// let vec = rmpp::pack_json(json);
+/// let vec = rmpp::pack_json(json).unwrap();
 /// assert_eq!(vec![0xC3], vec);
 /// ```
-#[wasm_bindgen]
-pub fn pack_json(json: &str) -> Vec<u8> {
-    let mut buffer: Vec<u8> = vec![];
-    let entry: MsgPackEntry = serde_json::from_str(json).unwrap();
-    write_value(&mut buffer, &entry.data).unwrap();
-    buffer
+/// 
+/// The json is checked with `validate_json` first, so mismatches are reported along with their path
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn pack_json(json: &str) -> Result<Vec<u8>, JsonError> {
+    let json: serde_json::Value = serde_json::from_str(json).map_err(json_error)?;
+    validate_entry(&json, "").map_err(json_error)?;
+
+    let entry: MsgPackEntry = serde_json::from_value(json).map_err(json_error)?;
+
+    try_pack(&entry).map_err(json_error)
+}
+
+/// Turns a marker byte and the json of a value into a MessagePack-encoded buffer, deriving the variant from the marker
+/// 
+/// The value has the shape of `data.value` in the json `pack_json` takes, and is left out (`null`) for Null. 
+/// Extension markers hold a timestamp when the value is an object of `seconds` and `nanos`. 
+/// The value has to encode with the very marker given, e.g. a FixStr marker has to match the length of the string
+/// 
+/// # Examples 
+/// 
+/// ```
+/// assert_eq!(vec![0xCD, 0x01, 0x2C], rmpp::pack_from_marker(0xCD, "300").unwrap());
+/// assert_eq!(vec![0xA2, 0x68, 0x69], rmpp::pack_from_marker(0xA2, r###""hi""###).unwrap());
+/// assert_eq!(vec![0xC4, 0x01, 0xFF], rmpp::pack_from_marker(0xC4, "[255]").unwrap());
+/// assert_eq!(vec![0xC0], rmpp::pack_from_marker(0xC0, "null").unwrap());
+/// assert_eq!(
+///     vec![0xD6, 0xFF, 0x00, 0x00, 0x00, 0x01], 
+///     rmpp::pack_from_marker(0xD6, r###"{"seconds":1,"nanos":0}"###).unwrap()
+/// );
+/// ```
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn pack_from_marker(raw_marker: u8, value_json: &str) -> Result<Vec<u8>, JsonError> {
+    let value: serde_json::Value = serde_json::from_str(value_json).map_err(json_error)?;
+    let (Some(value_type), Some(basic_type)) = (marker_variant(raw_marker, &value), classify_marker(raw_marker)) else {
+        return Err(json_error("Reserved marker 0xC1"));
+    };
+
+    let mut data = serde_json::Map::new();
+    data.insert("type".to_string(), serde_json::Value::from(value_type));
+    if value_type != "Null" { data.insert("value".to_string(), value); }
+
+    let mut json = serde_json::Map::new();
+    json.insert("raw_marker".to_string(), serde_json::Value::from(raw_marker));
+    json.insert("basic_type".to_string(), serde_json::to_value(basic_type).map_err(json_error)?);
+    json.insert("data".to_string(), serde_json::Value::Object(data));
+    let json = serde_json::Value::Object(json);
+
+    validate_entry(&json, "").map_err(json_error)?;
+    let entry: MsgPackEntry = serde_json::from_value(json).map_err(json_error)?;
+
+    let buf: Vec<u8> = try_pack(&entry).map_err(json_error)?;
+    if buf[0] != raw_marker {
+        return Err(json_error(format!("Marker 0x{:02X} doesn't match the value, which packs with 0x{:02X}", raw_marker, buf[0])));
+    }
+    Ok(buf)
+}
+
+/// Returns the name of the MsgPackValue variant a marker byte stands for, the value tells extensions and timestamps apart
+fn marker_variant(raw_marker: u8, value: &serde_json::Value) -> Option<&'static str> {
+    let name: &str = match Marker::from_u8(raw_marker) {
+        Marker::Null => "Null",
+        Marker::True|Marker::False => "Bool",
+        Marker::FixPos(_) => "FixPos",
+        Marker::FixNeg(_) => "FixNeg",
+        Marker::U8  => "U8",
+        Marker::U16 => "U16",
+        Marker::U32 => "U32",
+        Marker::U64 => "U64",
+        Marker::I8  => "I8",
+        Marker::I16 => "I16",
+        Marker::I32 => "I32",
+        Marker::I64 => "I64",
+        Marker::F32 => "F32",
+        Marker::F64 => "F64",
+        Marker::FixStr(_) => "FixStr",
+        Marker::Str8  => "Str8",
+        Marker::Str16 => "Str16",
+        Marker::Str32 => "Str32",
+        Marker::Bin8  => "Bin8",
+        Marker::Bin16 => "Bin16",
+        Marker::Bin32 => "Bin32",
+        Marker::FixArray(_) => "FixArray",
+        Marker::Array16 => "Array16",
+        Marker::Array32 => "Array32",
+        Marker::FixMap(_) => "FixMap",
+        Marker::Map16 => "Map16",
+        Marker::Map32 => "Map32",
+        Marker::FixExt1|Marker::FixExt2|Marker::FixExt4|Marker::FixExt8|Marker::FixExt16|
+        Marker::Ext8|Marker::Ext16|Marker::Ext32 => if value.get("seconds").is_some() { "Timestamp" } else { "Ext" },
+        Marker::Reserved => return None
+    };
+    Some(name)
+}
+
+/// Checks that a json string is a json-encoded MsgPackEntry, reporting the path of the first mismatch
+/// 
+/// The expected shape is the one `unpack_json` produces:
+/// - An entry is an object of `raw_marker` (a byte), `basic_type` (a BasicTypes name) and `data`
+/// - `data` is an object of `type` (a MsgPackValue variant name) and `value`, which is left out for Null
+/// - Integers are numbers within the range of their type, floats are numbers or one of `"NaN"`, `"Infinity"`, `"-Infinity"`
+/// - Strings are strings, StrRaw is a base64 string, binary is an array of bytes or a hex string
+/// - Arrays are arrays of entries, maps are arrays of `[key, value]` entry pairs
+/// - Extensions are `[type, bytes]` pairs, timestamps are objects of `seconds` and `nanos`
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let json = r###"{"raw_marker":204,"basic_type":"Number","data":{"type":"U8","value":"1"}}"###;
+/// assert_eq!(
+///     "data.value: expected an integer for type U8", 
+///     rmpp::validate_json(json).unwrap_err().to_string()
+/// );
+/// 
+/// let json = r###"{"raw_marker":145,"basic_type":"Array","data":{"type":"FixArray","value":[{"raw_marker":192}]}}"###;
+/// assert_eq!(
+///     "data.value[0].basic_type: expected one of the basic type names", 
+///     rmpp::validate_json(json).unwrap_err().to_string()
+/// );
+/// 
+/// let json = r###"{"raw_marker":204,"basic_type":"Number","data":{"type":"U8","value":999}}"###;
+/// assert_eq!(
+///     "data.value: 999 is out of range 0..=255 for type U8", 
+///     rmpp::validate_json(json).unwrap_err().to_string()
+/// );
+/// 
+/// let json = r###"{"raw_marker":192,"basic_type":"Null","data":{"type":"Null"}}"###;
+/// assert!(rmpp::validate_json(json).is_ok());
+/// ```
+pub fn validate_json(json: &str) -> Result<(), MsgPackError> {
+    let json: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| MsgPackError::Custom(e.to_string()))?;
+
+    validate_entry(&json, "").map_err(MsgPackError::Custom)
 }
 
 /// Turns a MsgPackEntry object into a MessagePack-encoded buffer
 ///
+/// # Panics
+/// 
+/// Panics if a value doesn't fit its marker, e.g. a FixStr longer than 31 bytes 
+///     or a timestamp with more than 999999999 nanoseconds, `try_pack` returns the error instead
+/// 
 /// # Examples 
 /// 
 /// ```
@@ -43,9 +182,748 @@ pub fn pack_json(json: &str) -> Vec<u8> {
 /// assert_eq!(vec![0xC3], vec);
 /// ```
 pub fn pack(entry: &MsgPackEntry) -> Vec<u8> {
+    try_pack(entry).unwrap()
+}
+
+/// Appends a MessagePack-encoded MsgPackEntry object to an existing buffer
+///
+/// # Panics
+/// 
+/// Panics if a value doesn't fit its marker, e.g. a FixStr longer than 31 bytes 
+///     or a timestamp with more than 999999999 nanoseconds, `write_value` returns the error instead
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let entry = rmpp::MsgPackEntry::new(
+///     195, rmpp::MsgPackValue::Bool(true)
+/// );
+/// 
+/// let mut buffer: Vec<u8> = vec![0xC0];
+/// rmpp::pack_into(&entry, &mut buffer);
+/// assert_eq!(vec![0xC0, 0xC3], buffer);
+/// ```
+pub fn pack_into(entry: &MsgPackEntry, buf: &mut Vec<u8>) {
+    write_value(buf, &entry.data).unwrap();
+}
+
+/// Turns a MsgPackEntry object into a MessagePack-encoded buffer, returning an error instead of panicking
+///
+/// # Examples 
+/// 
+/// ```
+/// let entry = rmpp::MsgPackEntry::new(
+///     195, rmpp::MsgPackValue::Bool(true)
+/// );
+/// 
+/// let vec = rmpp::try_pack(&entry).unwrap();
+/// assert_eq!(vec![0xC3], vec);
+/// ```
+pub fn try_pack(entry: &MsgPackEntry) -> Result<Vec<u8>, MsgPackError> {
     let mut buffer: Vec<u8> = vec![];
-    write_value(&mut buffer, &entry.data).unwrap();
-    buffer
+    write_value(&mut buffer, &entry.data)?;
+    Ok(buffer)
+}
+
+/// Writes a MsgPackEntry object as a frame prefixed with its 4-byte big-endian length, see `FramedReader`
+/// 
+/// The frame is assembled in a buffer sized with `encoded_len` and written at once, 
+///     so an entry that fails to encode leaves nothing half-written
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let mut stream: Vec<u8> = vec![];
+/// rmpp::write_frame(&mut stream, &rmpp::msgpack!([true, null])).unwrap();
+/// assert_eq!(vec![0x00, 0x00, 0x00, 0x03, 0x92, 0xC3, 0xC0], stream);
+/// 
+/// let values: Vec<_> = rmpp::FramedReader::new(&stream[..]).collect();
+/// assert_eq!(rmpp::msgpack!([true, null]), *values[0].as_ref().unwrap());
+/// ```
+pub fn write_frame<W: Write>(writer: &mut W, entry: &MsgPackEntry) -> Result<(), MsgPackError> {
+    let mut frame: Vec<u8> = vec![];
+    frame_into(&mut frame, entry)?;
+
+    writer.write_all(&frame)?;
+    Ok(())
+}
+
+/// Appends a MsgPackEntry object to a buffer as a frame prefixed with its 4-byte big-endian length
+fn frame_into(buf: &mut Vec<u8>, entry: &MsgPackEntry) -> Result<(), MsgPackError> {
+    let len: usize = encoded_len(entry);
+    let prefix: u32 = u32::try_from(len)
+        .map_err(|_| MsgPackError::Custom(format!("Frame of {} bytes doesn't fit a 4-byte length", len)))?;
+
+    buf.reserve(4 + len);
+    buf.extend_from_slice(&prefix.to_be_bytes());
+    write_value(buf, &entry.data)
+}
+
+/// Writes MsgPackEntry objects to a writer one after another, the encode counterpart of `MsgPackReader`
+/// 
+/// Every value is encoded into a scratch buffer kept across calls and handed to the writer in a single write, 
+///     so a value that fails to encode leaves nothing half-written and no allocation is made once the buffer has grown
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let mut encoder = rmpp::Encoder::new(Vec::new());
+/// encoder.write_entry(&rmpp::msgpack!(true)).unwrap();
+/// encoder.write_entry(&rmpp::msgpack!([1, "a"])).unwrap();
+/// encoder.write_frame(&rmpp::msgpack!(null)).unwrap();
+/// 
+/// let output: Vec<u8> = encoder.finish().unwrap();
+/// assert_eq!(vec![0xC3, 0x92, 0x01, 0xA1, 0x61, 0x00, 0x00, 0x00, 0x01, 0xC0], output);
+/// ```
+pub struct Encoder<W: Write> {
+    writer: W,
+    /// Scratch buffer of the value being written
+    buf: Vec<u8>,
+}
+impl<W: Write> Encoder<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, buf: vec![] }
+    }
+
+    /// Writes a single value
+    pub fn write_entry(&mut self, entry: &MsgPackEntry) -> Result<(), MsgPackError> {
+        self.buf.clear();
+        write_value(&mut self.buf, &entry.data)?;
+        self.writer.write_all(&self.buf)?;
+        Ok(())
+    }
+
+    /// Writes a value as a frame prefixed with its 4-byte big-endian length, see `write_frame`
+    pub fn write_frame(&mut self, entry: &MsgPackEntry) -> Result<(), MsgPackError> {
+        self.buf.clear();
+        frame_into(&mut self.buf, entry)?;
+        self.writer.write_all(&self.buf)?;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer
+    pub fn flush(&mut self) -> Result<(), MsgPackError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer and hands it back
+    pub fn finish(mut self) -> Result<W, MsgPackError> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Returns a reference to the underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+}
+
+/// Writes a MessagePack value like `write_value`, failing as soon as the output would exceed `max_bytes`
+/// 
+/// Bytes are counted as they're written, so nothing gets buffered and `encoded_len` isn't needed upfront. 
+/// The write that would cross the limit is refused, while everything written before it stays in the writer. 
+/// Returns the number of bytes written
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let entry = rmpp::msgpack!(["abc", 1]);
+/// 
+/// let mut frame: Vec<u8> = vec![];
+/// assert_eq!(6, rmpp::write_value_limited(&mut frame, &entry, 6).unwrap());
+/// assert_eq!(rmpp::pack(&entry), frame);
+/// 
+/// let mut frame: Vec<u8> = vec![];
+/// let e = rmpp::write_value_limited(&mut frame, &entry, 5).unwrap_err();
+/// assert_eq!("Output limit of 5 bytes exceeded", e.to_string());
+/// assert!(frame.len() <= 5);
+/// ```
+pub fn write_value_limited<W: Write, V: MsgValue>(writer: &mut W, value: &V, max_bytes: usize) -> Result<usize, MsgPackError> {
+    let mut limited = LimitedWriter { writer, remaining: max_bytes, exceeded: false };
+
+    match write_value(&mut limited, value) {
+        Err(_) if limited.exceeded => Err(MsgPackError::Custom(format!("Output limit of {} bytes exceeded", max_bytes))),
+        res => res.map(|_| max_bytes - limited.remaining)
+    }
+}
+
+/// Writer passing bytes through until its limit is reached
+struct LimitedWriter<'a, W: Write> {
+    writer: &'a mut W,
+    /// Bytes left before the limit
+    remaining: usize,
+    /// Whether a write was refused for crossing the limit
+    exceeded: bool,
+}
+impl<W: Write> Write for LimitedWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() > self.remaining {
+            self.exceeded = true;
+            return Err(io::Error::from(io::ErrorKind::WriteZero));
+        }
+
+        let written: usize = self.writer.write(buf)?;
+        self.remaining -= written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Computes the size of a MessagePack-encoded MsgPackEntry object without encoding it
+///
+/// # Examples 
+/// 
+/// ```
+/// let entry = rmpp::MsgPackBuilder::new()
+///     .map(|b| b
+///         .str("a").array(|b| b.int(-200).uint(70000).float(0.5).bin(&[1, 2, 3]))
+///         .str("b").ext(1, &[0; 16]).str("c").ext(2, &[0; 3]).str("d").timestamp(-1, 0)
+///     )
+///     .build();
+/// assert_eq!(rmpp::pack(&entry).len(), rmpp::encoded_len(&entry));
+/// 
+/// let cases: Vec<Vec<u8>> = vec![
+///     vec![0xC0], vec![0x7F], vec![0xE0], vec![0xCC, 0x00], vec![0xD1, 0x00, 0x01],
+///     vec![0xCA, 0x3F, 0x80, 0x00, 0x00], vec![0xA1, 0x61], vec![0xDA, 0x00, 0x01, 0x61],
+///     vec![0xC6, 0x00, 0x00, 0x00, 0x01, 0xFF], vec![0xDC, 0x00, 0x01, 0xC0], vec![0xDF, 0x00, 0x00, 0x00, 0x01, 0xC0, 0xC0],
+///     vec![0xD6, 0xFF, 0x00, 0x00, 0x00, 0x01], vec![0xD7, 0xFF, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01],
+/// ];
+/// for case in cases {
+///     assert_eq!(case.len(), rmpp::encoded_len(&rmpp::unpack(&case).unwrap()), "{:02X?}", case);
+/// }
+/// ```
+pub fn encoded_len(entry: &MsgPackEntry) -> usize {
+    value_len(&entry.data)
+}
+
+/// Computes the size of a MessagePack-encoded MsgPackValue object
+fn value_len(value: &MsgPackValue) -> usize {
+    match value {
+        // Null, Bool and Fixed Integer
+        MsgPackValue::Null|MsgPackValue::Bool(_)|MsgPackValue::FixPos(_)|MsgPackValue::FixNeg(_) => 1,
+        // Integer and Float
+        MsgPackValue::U8(_)|MsgPackValue::I8(_) => 2,
+        MsgPackValue::U16(_)|MsgPackValue::I16(_) => 3,
+        MsgPackValue::U32(_)|MsgPackValue::I32(_)|MsgPackValue::F32(_) => 5,
+        MsgPackValue::U64(_)|MsgPackValue::I64(_)|MsgPackValue::F64(_) => 9,
+        // String
+        MsgPackValue::FixStr(s) => 1 + s.len(),
+        MsgPackValue::Str8(s) => 2 + s.len(),
+        MsgPackValue::Str16(s) => 3 + s.len(),
+        MsgPackValue::Str32(s) => 5 + s.len(),
+        MsgPackValue::StrRaw(b) => {
+            let header: usize = match value2marker(value) { markers::STR8 => 2, markers::STR16 => 3, markers::STR32 => 5, _ => 1 };
+            header + b.len()
+        },
+        // Binary
+        MsgPackValue::Bin8(b) => 2 + b.len(),
+        MsgPackValue::Bin16(b) => 3 + b.len(),
+        MsgPackValue::Bin32(b) => 5 + b.len(),
+        // Array
+        MsgPackValue::FixArray(values) => 1 + values.iter().map(encoded_len).sum::<usize>(),
+        MsgPackValue::Array16(values) => 3 + values.iter().map(encoded_len).sum::<usize>(),
+        MsgPackValue::Array32(values) => 5 + values.iter().map(encoded_len).sum::<usize>(),
+        // Map
+        MsgPackValue::FixMap(values) => 1 + values.iter().map(|(k, v)| encoded_len(k) + encoded_len(v)).sum::<usize>(),
+        MsgPackValue::Map16(values) => 3 + values.iter().map(|(k, v)| encoded_len(k) + encoded_len(v)).sum::<usize>(),
+        MsgPackValue::Map32(values) => 5 + values.iter().map(|(k, v)| encoded_len(k) + encoded_len(v)).sum::<usize>(),
+        // Extension - the header holds the type along with the length
+        MsgPackValue::Ext(_, b) => {
+            let header: usize = match value2marker(value) { markers::EXT8 => 3, markers::EXT16 => 4, markers::EXT32 => 6, _ => 2 };
+            header + b.len()
+        },
+        MsgPackValue::Timestamp { .. } => {
+            match value2marker(value) { markers::FIXEXT4 => 6, markers::FIXEXT8 => 10, _ => 15 }
+        }
+    }
+}
+
+/// Decodes a MessagePack-encoded buffer and re-encodes it, returning whether the bytes came out the same
+/// 
+/// Every marker is preserved, except for extensions and timestamps which always re-encode with the narrowest marker
+///
+/// # Examples 
+/// 
+/// ```
+/// let cases: Vec<Vec<u8>> = vec![
+///     // Null and Boolean
+///     vec![0xC0], vec![0xC2], vec![0xC3],
+///     // Fixed Integer boundaries
+///     vec![0x00], vec![0x7F], vec![0xE0], vec![0xFF],
+///     // Unsigned and Signed Integer
+///     vec![0xCC, 0x00], vec![0xCD, 0x00, 0x01], vec![0xCE, 0x00, 0x00, 0x00, 0x01], [&[0xCF][..], &[0xFF; 8]].concat(),
+///     vec![0xD0, 0x80], vec![0xD1, 0x00, 0x01], vec![0xD2, 0xFF, 0xFF, 0xFF, 0xFF], [&[0xD3][..], &[0x7F; 8]].concat(),
+///     // Float
+///     vec![0xCA, 0x3F, 0x80, 0x00, 0x00], vec![0xCB, 0x3F, 0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+///     // String, including the 31 byte FixStr limit
+///     vec![0xA0], [&[0xBF][..], &[0x61; 31]].concat(), [&[0xD9, 0x20][..], &[0x61; 32]].concat(),
+///     vec![0xD9, 0x00], vec![0xDA, 0x00, 0x01, 0x61], vec![0xDB, 0x00, 0x00, 0x00, 0x01, 0x61],
+///     // Binary
+///     vec![0xC4, 0x00], vec![0xC5, 0x00, 0x01, 0xFF], vec![0xC6, 0x00, 0x00, 0x00, 0x01, 0xFF],
+///     // Array, including the 15 element FixArray limit
+///     vec![0x90], [&[0x9F][..], &[0xC0; 15]].concat(), [&[0xDC, 0x00, 0x10][..], &[0xC0; 16]].concat(),
+///     vec![0xDC, 0x00, 0x00], vec![0xDD, 0x00, 0x00, 0x00, 0x01, 0xC0],
+///     // Map, including the 15 pair FixMap limit
+///     vec![0x80], [&[0x8F][..], &[0xC0; 30]].concat(), [&[0xDE, 0x00, 0x10][..], &[0xC0; 32]].concat(),
+///     vec![0xDE, 0x00, 0x00], vec![0xDF, 0x00, 0x00, 0x00, 0x01, 0xC0, 0xC0],
+///     // Extension and Timestamp
+///     vec![0xD4, 0x01, 0xFF], vec![0xD8, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+///     vec![0xC7, 0x03, 0x01, 0x61, 0x62, 0x63],
+///     vec![0xD6, 0xFF, 0x00, 0x00, 0x00, 0x01], vec![0xD7, 0xFF, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01],
+///     [&[0xC7, 0x0C, 0xFF, 0x00, 0x00, 0x00, 0x00][..], &[0xFF; 8]].concat(),
+/// ];
+/// 
+/// for case in cases {
+///     assert!(rmpp::roundtrip(&case).unwrap(), "{:02X?}", case);
+/// }
+/// 
+/// // Extensions always get the narrowest marker
+/// assert!(!rmpp::roundtrip(&[0xC7, 0x01, 0x01, 0xFF]).unwrap());
+/// ```
+pub fn roundtrip(data: &[u8]) -> Result<bool, MsgPackError> {
+    let entry: MsgPackEntry = unpack(data)?;
+    Ok(try_pack(&entry)? == data)
+}
+
+/// Turns a MsgPackEntry object into a MessagePack-encoded buffer, making sure every raw marker matches its value
+/// 
+/// The marker is always derived from the value, so a mismatching raw marker means the entry 
+///     wouldn't be encoded the way it claims to be
+///
+/// # Examples 
+/// 
+/// ```
+/// let entry = rmpp::MsgPackEntry::new(
+///     195, rmpp::MsgPackValue::Bool(true)
+/// );
+/// assert_eq!(vec![0xC3], rmpp::pack_checked(&entry).unwrap());
+/// 
+/// let entry = rmpp::MsgPackEntry::new(
+///     207, rmpp::MsgPackValue::FixPos(1)
+/// );
+/// assert!(rmpp::pack_checked(&entry).is_err());
+/// ```
+pub fn pack_checked(entry: &MsgPackEntry) -> Result<Vec<u8>, MsgPackError> {
+    check_markers(entry)?;
+    try_pack(entry)
+}
+
+/// Recursively checks that every raw marker matches the marker its value gets encoded with
+fn check_markers(entry: &MsgPackEntry) -> Result<(), MsgPackError> {
+    let marker: u8 = value2marker(&entry.data);
+    if entry.raw_marker != marker {
+        return Err(MsgPackError::Custom(format!(
+            "Raw marker 0x{:02X} doesn't match the {:?} value encoded with 0x{:02X}", entry.raw_marker, entry.basic_type, marker
+        )));
+    }
+
+    if let Some(values) = entry.data.as_array() {
+        for v in values { check_markers(v)?; }
+    }
+    if let Some(values) = entry.data.as_map() {
+        for (k, v) in values { check_markers(k)?; check_markers(v)?; }
+    }
+
+    Ok(())
+}
+
+/// Turns a MsgPackEntry object into a MessagePack-encoded buffer using the narrowest marker for every value
+/// 
+/// Integers collapse to the smallest integer family, strings, binary, arrays and maps to the smallest length field. 
+/// Floats are kept as they are, even integral ones never become integers, see `pack_minimal_floats` to narrow them
+///
+/// # Panics
+/// 
+/// Panics if a timestamp holds more than 999999999 nanoseconds, every other value gets a marker that fits it. 
+/// `try_pack` returns the error instead
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let entry = rmpp::MsgPackEntry::new(
+///     207, rmpp::MsgPackValue::U64(1)
+/// );
+/// 
+/// let vec = rmpp::pack_minimal(&entry);
+/// assert_eq!(vec![0x01], vec);
+/// 
+/// let input = vec![0xDC, 0x00, 0x02, 0xD9, 0x01, 0x61, 0xD3, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+/// let entry = rmpp::unpack(&input).unwrap();
+/// 
+/// let vec = rmpp::pack_minimal(&entry);
+/// assert_eq!(vec![0x92, 0xA1, 0x61, 0xFF], vec);
+/// ```
+pub fn pack_minimal(entry: &MsgPackEntry) -> Vec<u8> {
+    pack(&minimize(entry, false))
+}
+
+/// Turns a MsgPackEntry object into a MessagePack-encoded buffer like `pack_minimal`, 
+///     also narrowing F64 to F32 whenever that's lossless
+/// 
+/// Only floats that come back from F32 with the very same bits are narrowed, 
+///     and floats still never become integers
+/// 
+/// # Panics
+/// 
+/// Panics if a timestamp holds more than 999999999 nanoseconds, every other value gets a marker that fits it. 
+/// `try_pack` returns the error instead
+/// 
+/// # Examples 
+/// 
+/// ```
+/// use rmpp::{MsgPackEntry, MsgPackValue};
+/// 
+/// let vec = rmpp::pack_minimal_floats(&MsgPackEntry::from(MsgPackValue::F64(0.5)));
+/// assert_eq!(vec![0xCA, 0x3F, 0x00, 0x00, 0x00], vec);
+/// 
+/// // Integral floats stay floats
+/// let vec = rmpp::pack_minimal_floats(&MsgPackEntry::from(MsgPackValue::F64(1.0)));
+/// assert_eq!(vec![0xCA, 0x3F, 0x80, 0x00, 0x00], vec);
+/// 
+/// // 0.1 has no exact F32 counterpart
+/// let entry = MsgPackEntry::from(MsgPackValue::F64(0.1));
+/// assert_eq!(rmpp::pack(&entry), rmpp::pack_minimal_floats(&entry));
+/// ```
+pub fn pack_minimal_floats(entry: &MsgPackEntry) -> Vec<u8> {
+    pack(&minimize(entry, true))
+}
+
+/// Lookup table of the reflected CRC-32 polynomial
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i: usize = 0;
+    while i < 256 {
+        let mut crc: u32 = i as u32;
+        let mut bit: usize = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Computes the CRC-32 checksum used by zlib and PNG
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, b| CRC32_TABLE[((crc ^ u32::from(*b)) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+/// Rebuilds an entry using the narrowest marker for every value, narrowing F64 to F32 only if asked to and lossless
+fn minimize(entry: &MsgPackEntry, floats: bool) -> MsgPackEntry {
+    let value: MsgPackValue = match &entry.data {
+        // Integer
+        MsgPackValue::FixPos(_)|MsgPackValue::FixNeg(_)|
+        MsgPackValue::U8(_)|MsgPackValue::U16(_)|MsgPackValue::U32(_)|MsgPackValue::U64(_)|
+        MsgPackValue::I8(_)|MsgPackValue::I16(_)|MsgPackValue::I32(_)|MsgPackValue::I64(_) => {
+            match entry.data.as_u64() {
+                Some(n) => uint2value(n),
+                None => int2value(entry.data.as_i64().unwrap()) // Anything that isn't u64 fits i64
+            }
+        },
+        // String
+        MsgPackValue::FixStr(s)|MsgPackValue::Str8(s)|
+        MsgPackValue::Str16(s)|MsgPackValue::Str32(s) => str2value(s.clone()),
+        // Binary
+        MsgPackValue::Bin8(b)|MsgPackValue::Bin16(b)|MsgPackValue::Bin32(b) => bin2value(b.clone()),
+        // Array
+        MsgPackValue::FixArray(values)|MsgPackValue::Array16(values)|MsgPackValue::Array32(values) => {
+            array2value(values.iter().map(|v| minimize(v, floats)).collect())
+        },
+        // Map
+        MsgPackValue::FixMap(values)|MsgPackValue::Map16(values)|MsgPackValue::Map32(values) => {
+            map2value(values.iter().map(|(k, v)| (minimize(k, floats), minimize(v, floats))).collect())
+        },
+        // Float - comparing the bits keeps -0.0 and NaN payloads too
+        MsgPackValue::F64(n) if floats && f64::from(*n as f32).to_bits() == n.to_bits() => MsgPackValue::F32(*n as f32),
+        // Everything else is either fixed or already the narrowest
+        value => value.clone()
+    };
+
+    MsgPackEntry::from(value)
+}
+
+/// Turns a MsgPackEntry object into a MessagePack-encoded buffer using the widest marker for every value
+/// 
+/// The opposite of `pack_minimal`, meant for exercising the large-header paths of decoders: 
+///     non-negative integers become U64, negative ones I64, floats F64, 
+///     strings, binary, arrays and maps get the 32-bit length field. 
+/// Extensions and timestamps keep the marker their data length calls for
+/// 
+/// # Panics
+/// 
+/// Panics if a timestamp holds more than 999999999 nanoseconds, every other value gets a marker that fits it. 
+/// `try_pack` returns the error instead
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let input = vec![0x92, 0xA1, 0x61, 0xFF];
+/// let vec = rmpp::pack_widest(&rmpp::unpack(&input).unwrap());
+/// 
+/// assert_eq!(vec![
+///     0xDD, 0x00, 0x00, 0x00, 0x02, 0xDB, 0x00, 0x00, 0x00, 0x01, 0x61, 
+///     0xD3, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+/// ], vec);
+/// 
+/// // Both extremes decode to the same content
+/// let widest = rmpp::unpack(&vec).unwrap();
+/// assert!(widest.data.logically_eq(&rmpp::unpack(&input).unwrap().data));
+/// assert_eq!(input, rmpp::pack_minimal(&widest));
+/// ```
+pub fn pack_widest(entry: &MsgPackEntry) -> Vec<u8> {
+    pack(&widen(entry))
+}
+
+/// Recursively rebuilds a MsgPackEntry with the widest marker for every value
+fn widen(entry: &MsgPackEntry) -> MsgPackEntry {
+    let value: MsgPackValue = match &entry.data {
+        // Integer
+        MsgPackValue::FixPos(_)|MsgPackValue::FixNeg(_)|
+        MsgPackValue::U8(_)|MsgPackValue::U16(_)|MsgPackValue::U32(_)|MsgPackValue::U64(_)|
+        MsgPackValue::I8(_)|MsgPackValue::I16(_)|MsgPackValue::I32(_)|MsgPackValue::I64(_) => {
+            match entry.data.as_u64() {
+                Some(n) => MsgPackValue::U64(n),
+                None => MsgPackValue::I64(entry.data.as_i64().unwrap()) // Anything that isn't u64 fits i64
+            }
+        },
+        // Float
+        MsgPackValue::F32(n) => MsgPackValue::F64(f64::from(*n)),
+        // String
+        MsgPackValue::FixStr(s)|MsgPackValue::Str8(s)|
+        MsgPackValue::Str16(s)|MsgPackValue::Str32(s) => MsgPackValue::Str32(s.clone()),
+        // Binary
+        MsgPackValue::Bin8(b)|MsgPackValue::Bin16(b)|MsgPackValue::Bin32(b) => MsgPackValue::Bin32(b.clone()),
+        // Array
+        MsgPackValue::FixArray(values)|MsgPackValue::Array16(values)|MsgPackValue::Array32(values) => {
+            MsgPackValue::Array32(values.iter().map(widen).collect())
+        },
+        // Map
+        MsgPackValue::FixMap(values)|MsgPackValue::Map16(values)|MsgPackValue::Map32(values) => {
+            MsgPackValue::Map32(values.iter().map(|(k, v)| (widen(k), widen(v))).collect())
+        },
+        // Everything else has a single encoding for its data
+        value => value.clone()
+    };
+
+    MsgPackEntry::from(value)
+}
+
+/// Turns a MsgPackEntry object into a canonical MessagePack-encoded buffer
+/// 
+/// Every value gets the narrowest marker like with `pack_minimal`, leaving floats as they are, 
+///     and the entries of every map are sorted by the encoded bytes of their keys. 
+/// Equal content always produces the same bytes, so the result can be hashed or signed, 
+///     and packing a decoded canonical buffer again gives the same buffer back
+/// 
+/// # Panics
+/// 
+/// Panics if a timestamp holds more than 999999999 nanoseconds, every other value gets a marker that fits it. 
+/// `try_pack` returns the error instead
+/// 
+/// # Examples 
+/// 
+/// ```
+/// // {"b": 1, "a": {"d": true, "c": false}} with wide markers
+/// let input = vec![
+///     0xDE, 0x00, 0x02, 0xD9, 0x01, 0x62, 0xCD, 0x00, 0x01, 0xA1, 0x61, 
+///     0x82, 0xA1, 0x64, 0xC3, 0xA1, 0x63, 0xC2,
+/// ];
+/// let canonical = rmpp::pack_canonical(&rmpp::unpack(&input).unwrap());
+/// 
+/// assert_eq!(vec![0x82, 0xA1, 0x61, 0x82, 0xA1, 0x63, 0xC2, 0xA1, 0x64, 0xC3, 0xA1, 0x62, 0x01], canonical);
+/// assert_eq!(canonical, rmpp::pack_canonical(&rmpp::unpack(&canonical).unwrap()));
+/// ```
+pub fn pack_canonical(entry: &MsgPackEntry) -> Vec<u8> {
+    pack_canonical_with(entry, KeyOrder::ByEncodedBytes)
+}
+
+/// Order of map entries in the canonical encoding
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Sorted by the encoded bytes of their keys, markers included
+    #[default]
+    ByEncodedBytes,
+    /// String keys sorted by their UTF-8 bytes, followed by any other keys sorted by their encoded bytes
+    ByStringValue,
+    /// Kept in their original order
+    Unchanged,
+}
+
+/// Turns a MsgPackEntry object into a canonical MessagePack-encoded buffer like `pack_canonical`, 
+///     with the given order of map entries
+/// 
+/// Entries with equal keys keep their original order
+/// 
+/// # Panics
+/// 
+/// Panics if a timestamp holds more than 999999999 nanoseconds, every other value gets a marker that fits it. 
+/// `try_pack` returns the error instead
+/// 
+/// # Examples 
+/// 
+/// ```
+/// use rmpp::KeyOrder;
+/// 
+/// // A 32-byte key needs a Str8 marker, which sorts it after every FixStr
+/// let long: String = "a".repeat(32);
+/// let entry = rmpp::msgpack!({ "b": 1, long.as_str(): 2 });
+/// 
+/// let by_bytes = rmpp::unpack(&rmpp::pack_canonical_with(&entry, KeyOrder::ByEncodedBytes)).unwrap();
+/// assert_eq!(Some("b"), by_bytes.data.as_map().unwrap()[0].0.data.as_str());
+/// 
+/// let by_string = rmpp::unpack(&rmpp::pack_canonical_with(&entry, KeyOrder::ByStringValue)).unwrap();
+/// assert_eq!(Some(long.as_str()), by_string.data.as_map().unwrap()[0].0.data.as_str());
+/// 
+/// assert_eq!(rmpp::pack(&entry), rmpp::pack_canonical_with(&entry, KeyOrder::Unchanged));
+/// ```
+pub fn pack_canonical_with(entry: &MsgPackEntry, order: KeyOrder) -> Vec<u8> {
+    pack(&sort_keys(minimize(entry, false), order))
+}
+
+/// Sorts the entries of every map in the given order
+fn sort_keys(entry: MsgPackEntry, order: KeyOrder) -> MsgPackEntry {
+    let value: MsgPackValue = match entry.data {
+        // Array
+        MsgPackValue::FixArray(values)|MsgPackValue::Array16(values)|MsgPackValue::Array32(values) => {
+            array2value(values.into_iter().map(|v| sort_keys(v, order)).collect())
+        },
+        // Map
+        MsgPackValue::FixMap(values)|MsgPackValue::Map16(values)|MsgPackValue::Map32(values) => {
+            let mut pairs: Vec<_> = values.into_iter().map(|(k, v)| {
+                let k: MsgPackEntry = sort_keys(k, order);
+                let sort_key: (bool, Vec<u8>) = match (order, k.data.as_str()) {
+                    (KeyOrder::ByStringValue, Some(s)) => (false, s.as_bytes().to_vec()),
+                    _ => (true, pack(&k))
+                };
+                (sort_key, (k, sort_keys(v, order)))
+            }).collect();
+            if order != KeyOrder::Unchanged { pairs.sort_by(|(a, _), (b, _)| a.cmp(b)); }
+
+            map2value(pairs.into_iter().map(|(_, pair)| pair).collect())
+        },
+        value => value
+    };
+
+    MsgPackEntry::from(value)
+}
+
+/// Overwrites the integer found at the given offset of a MessagePack-encoded buffer in place, keeping its marker
+/// 
+/// The offset has to point at the marker of an integer, e.g. one reported by `unpack_json_with_offsets`. 
+/// Fails without touching the buffer if there's no integer there or if the new value doesn't fit its marker, 
+///     since a wider marker would shift everything after it
+/// 
+/// # Examples 
+/// 
+/// ```
+/// // ["n", U16(300)]
+/// let mut buf = vec![0x92, 0xA1, 0x6E, 0xCD, 0x01, 0x2C];
+/// 
+/// rmpp::patch_int_at(&mut buf, 3, 301).unwrap();
+/// assert_eq!(vec![0x92, 0xA1, 0x6E, 0xCD, 0x01, 0x2D], buf);
+/// 
+/// assert!(rmpp::patch_int_at(&mut buf, 3, 70000).is_err());
+/// assert!(rmpp::patch_int_at(&mut buf, 3, -1).is_err());
+/// assert!(rmpp::patch_int_at(&mut buf, 1, 1).is_err());
+/// 
+/// // Offsets past the end, including a marker whose payload got cut off
+/// let err = rmpp::patch_int_at(&mut buf, 6, 1).unwrap_err();
+/// assert_eq!("offset 6 out of bounds", err.to_string());
+/// assert!(rmpp::patch_int_at(&mut buf[..4], 3, 301).is_err());
+/// 
+/// // Fixints hold their value in the marker itself
+/// let mut buf = vec![0x05];
+/// rmpp::patch_int_at(&mut buf, 0, 127).unwrap();
+/// assert_eq!(vec![0x7F], buf);
+/// assert!(rmpp::patch_int_at(&mut buf, 0, 128).is_err());
+/// ```
+pub fn patch_int_at(buf: &mut [u8], offset: usize, n: i64) -> Result<(), MsgPackError> {
+    let out_of_bounds = || MsgPackError::Custom(format!("offset {} out of bounds", offset));
+    let marker: u8 = *buf.get(offset).ok_or_else(out_of_bounds)?;
+
+    let (fits, width): (bool, usize) = match marker {
+        markers::FIXPOS..=0x7F => ((0..=0x7F).contains(&n), 0),
+        markers::FIXNEG..=0xFF => ((-32..=-1).contains(&n), 0),
+        markers::U8  => (u8::try_from(n).is_ok(), 1),
+        markers::U16 => (u16::try_from(n).is_ok(), 2),
+        markers::U32 => (u32::try_from(n).is_ok(), 4),
+        markers::U64 => (n >= 0, 8),
+        markers::I8  => (i8::try_from(n).is_ok(), 1),
+        markers::I16 => (i16::try_from(n).is_ok(), 2),
+        markers::I32 => (i32::try_from(n).is_ok(), 4),
+        markers::I64 => (true, 8),
+        _ => return Err(MsgPackError::Custom(format!("No integer at offset {}, found marker 0x{:02X}", offset, marker)))
+    };
+    if !fits {
+        return Err(MsgPackError::Custom(format!("{} doesn't fit marker 0x{:02X} at offset {}", n, marker, offset)));
+    }
+
+    // Values that fit are the low bytes of the two's complement either way
+    match width {
+        0 => buf[offset] = n as u8,
+        _ => buf.get_mut(offset + 1..offset + 1 + width).ok_or_else(out_of_bounds)?
+            .copy_from_slice(&n.to_be_bytes()[8 - width..])
+    }
+    Ok(())
+}
+
+/// Checks that a value fits the range and the length limit of its marker
+fn check_range(value: &MsgPackValue) -> Result<(), MsgPackError> {
+    const U8: usize = u8::MAX as usize;
+    const U16: usize = u16::MAX as usize;
+    const U32: usize = u32::MAX as usize;
+
+    let (name, len, max): (&str, usize, usize) = match value {
+        // Fixed Integer
+        MsgPackValue::FixPos(n) if *n > 0x7F => {
+            return Err(MsgPackError::Custom(format!("FixPos can't hold {}, the range is 0..=127", n)));
+        },
+        MsgPackValue::FixNeg(n) if !(-32..=-1).contains(n) => {
+            return Err(MsgPackError::Custom(format!("FixNeg can't hold {}, the range is -32..=-1", n)));
+        },
+        // Timestamp - larger nanoseconds would spill into the seconds of Timestamp 64
+        MsgPackValue::Timestamp { nanos, .. } if *nanos > 999_999_999 => {
+            return Err(MsgPackError::Custom(format!("Timestamp can't hold {} nanoseconds, the range is 0..=999999999", nanos)));
+        },
+        // String
+        MsgPackValue::FixStr(s) => ("FixStr", s.len(), 31),
+        MsgPackValue::Str8(s)   => ("Str8",  s.len(), U8),
+        MsgPackValue::Str16(s)  => ("Str16", s.len(), U16),
+        MsgPackValue::Str32(s)  => ("Str32", s.len(), U32),
+        MsgPackValue::StrRaw(b) => ("StrRaw", b.len(), U32),
+        // Binary
+        MsgPackValue::Bin8(b)  => ("Bin8",  b.len(), U8),
+        MsgPackValue::Bin16(b) => ("Bin16", b.len(), U16),
+        MsgPackValue::Bin32(b) => ("Bin32", b.len(), U32),
+        // Array
+        MsgPackValue::FixArray(values) => ("FixArray", values.len(), 15),
+        MsgPackValue::Array16(values)  => ("Array16", values.len(), U16),
+        MsgPackValue::Array32(values)  => ("Array32", values.len(), U32),
+        // Map
+        MsgPackValue::FixMap(values) => ("FixMap", values.len(), 15),
+        MsgPackValue::Map16(values)  => ("Map16", values.len(), U16),
+        MsgPackValue::Map32(values)  => ("Map32", values.len(), U32),
+        // Extension
+        MsgPackValue::Ext(_, b) => ("Ext", b.len(), U32),
+        _ => return Ok(())
+    };
+
+    if len > max {
+        return Err(MsgPackError::Custom(format!("{} can't hold a length of {}, the limit is {}", name, len, max)));
+    }
+
+    Ok(())
 }
 
 /// Serializes and writes a MsgValue-enabled object to a given buffer
@@ -60,16 +938,28 @@ pub fn pack(entry: &MsgPackEntry) -> Vec<u8> {
 /// let value = rmpp::MsgPackValue::Bool(true);
 /// rmpp::write_value(&mut buffer, &value);
 /// assert_eq!(vec![0xC3], buffer);
+/// 
+/// let mut buffer: Vec<u8> = vec![];
+/// let value = rmpp::MsgPackValue::Timestamp { seconds: 1, nanos: 0 };
+/// rmpp::write_value(&mut buffer, &value);
+/// assert_eq!(vec![0xD6, 0xFF, 0x00, 0x00, 0x00, 0x01], buffer);
+/// 
+/// // Values that don't fit their marker are rejected
+/// let value = rmpp::MsgPackValue::FixStr("a".repeat(40));
+/// assert!(rmpp::write_value(&mut vec![], &value).is_err());
 /// ```
-pub fn write_value<W: Write, V: MsgValue>(writer: &mut W, value: &V) -> std::io::Result<()> {
+pub fn write_value<W: Write, V: MsgValue>(writer: &mut W, value: &V) -> Result<(), MsgPackError> {
+    // A value that doesn't fit its marker would produce a malformed buffer
+    check_range(value.get_value())?;
+
     match value.get_value() {
         // Null
         MsgPackValue::Null => {
-            writer.write_all(&[0xC0])?;
+            writer.write_all(&[markers::NULL])?;
         },
         // Bool
         MsgPackValue::Bool(b) => {
-            writer.write_all(&[if !*b { 0xC2 } else { 0xC3 }])?;
+            writer.write_all(&[if !*b { markers::FALSE } else { markers::TRUE }])?;
         },
         // Fixed Integer
         MsgPackValue::FixPos(n) => {
@@ -81,45 +971,45 @@ pub fn write_value<W: Write, V: MsgValue>(writer: &mut W, value: &V) -> std::io:
         },
         // Unsigned Integer
         MsgPackValue::U8(n) => {
-            writer.write_all(&[0xCC])?;
+            writer.write_all(&[markers::U8])?;
             writer.write_all(&[*n])?;
         },
         MsgPackValue::U16(n) => {
-            writer.write_all(&[0xCD])?;
+            writer.write_all(&[markers::U16])?;
             writer.write_all(&(*n).to_be_bytes())?;
         },
         MsgPackValue::U32(n) => {
-            writer.write_all(&[0xCE])?;
+            writer.write_all(&[markers::U32])?;
             writer.write_all(&(*n).to_be_bytes())?;
         },
         MsgPackValue::U64(n) => {
-            writer.write_all(&[0xCF])?;
+            writer.write_all(&[markers::U64])?;
             writer.write_all(&(*n).to_be_bytes())?;
         },
         // Signed Integer
         MsgPackValue::I8(n) => {
-            writer.write_all(&[0xD0])?;
+            writer.write_all(&[markers::I8])?;
             writer.write_all(&[*n as u8])?;
         },
         MsgPackValue::I16(n) => {
-            writer.write_all(&[0xD1])?;
+            writer.write_all(&[markers::I16])?;
             writer.write_all(&(*n).to_be_bytes())?;
         },
         MsgPackValue::I32(n) => {
-            writer.write_all(&[0xD2])?;
+            writer.write_all(&[markers::I32])?;
             writer.write_all(&(*n).to_be_bytes())?;
         },
         MsgPackValue::I64(n) => {
-            writer.write_all(&[0xD3])?;
+            writer.write_all(&[markers::I64])?;
             writer.write_all(&(*n).to_be_bytes())?;
         },
         // Float
         MsgPackValue::F32(n) => {
-            writer.write_all(&[0xCA])?;
+            writer.write_all(&[markers::F32])?;
             writer.write_all(&(*n).to_be_bytes())?;
         },
         MsgPackValue::F64(n) => {
-            writer.write_all(&[0xCB])?;
+            writer.write_all(&[markers::F64])?;
             writer.write_all(&(*n).to_be_bytes())?;
         },
         // String
@@ -131,35 +1021,47 @@ pub fn write_value<W: Write, V: MsgValue>(writer: &mut W, value: &V) -> std::io:
         },
         MsgPackValue::Str8(s) => {
             let bytes = s.as_bytes();
-            writer.write_all(&[0xD9])?;
+            writer.write_all(&[markers::STR8])?;
             writer.write_all(&[bytes.len() as u8])?;
             writer.write_all(bytes)?;
         },
         MsgPackValue::Str16(s) => {
             let bytes = s.as_bytes();
-            writer.write_all(&[0xDA])?;
+            writer.write_all(&[markers::STR16])?;
             writer.write_all(&(bytes.len() as u16).to_be_bytes())?;
             writer.write_all(bytes)?;
         },
         MsgPackValue::Str32(s) => {
             let bytes = s.as_bytes();
-            writer.write_all(&[0xDB])?;
+            writer.write_all(&[markers::STR32])?;
             writer.write_all(&(bytes.len() as u32).to_be_bytes())?;
             writer.write_all(bytes)?;
         },
+        MsgPackValue::StrRaw(b) => {
+            // The narrowest string marker is used
+            let marker: u8 = value2marker(value.get_value());
+            writer.write_all(&[marker])?;
+            match marker {
+                markers::STR8 => writer.write_all(&[b.len() as u8])?,
+                markers::STR16 => writer.write_all(&(b.len() as u16).to_be_bytes())?,
+                markers::STR32 => writer.write_all(&(b.len() as u32).to_be_bytes())?,
+                _ => {} // FixStr has the length encoded inside of the marker
+            }
+            writer.write_all(b)?;
+        },
         // Binary
         MsgPackValue::Bin8(b) => {
-            writer.write_all(&[0xC4])?;
+            writer.write_all(&[markers::BIN8])?;
             writer.write_all(&[b.len() as u8])?;
             writer.write_all(b)?;
         },
         MsgPackValue::Bin16(b) => {
-            writer.write_all(&[0xC5])?;
+            writer.write_all(&[markers::BIN16])?;
             writer.write_all(&(b.len() as u16).to_be_bytes())?;
             writer.write_all(b)?;
         },
         MsgPackValue::Bin32(b) => {
-            writer.write_all(&[0xC6])?;
+            writer.write_all(&[markers::BIN32])?;
             writer.write_all(&(b.len() as u32).to_be_bytes())?;
             writer.write_all(b)?;
         },
@@ -174,7 +1076,7 @@ pub fn write_value<W: Write, V: MsgValue>(writer: &mut W, value: &V) -> std::io:
             }
         },
         MsgPackValue::Array16(values) => {
-            writer.write_all(&[0xDC])?;
+            writer.write_all(&[markers::ARRAY16])?;
             writer.write_all(&(values.len() as u16).to_be_bytes())?;
 
             // Recursively write each element
@@ -183,7 +1085,7 @@ pub fn write_value<W: Write, V: MsgValue>(writer: &mut W, value: &V) -> std::io:
             }
         },
         MsgPackValue::Array32(values) => {
-            writer.write_all(&[0xDD])?;
+            writer.write_all(&[markers::ARRAY32])?;
             writer.write_all(&(values.len() as u32).to_be_bytes())?;
 
             // Recursively write each element
@@ -203,7 +1105,7 @@ pub fn write_value<W: Write, V: MsgValue>(writer: &mut W, value: &V) -> std::io:
             }
         },
         MsgPackValue::Map16(values) => {
-            writer.write_all(&[0xDE])?;
+            writer.write_all(&[markers::MAP16])?;
             writer.write_all(&(values.len() as u16).to_be_bytes())?;
             
             // Recursively write each element
@@ -213,7 +1115,7 @@ pub fn write_value<W: Write, V: MsgValue>(writer: &mut W, value: &V) -> std::io:
             }
         },
         MsgPackValue::Map32(values) => {
-            writer.write_all(&[0xDF])?;
+            writer.write_all(&[markers::MAP32])?;
             writer.write_all(&(values.len() as u32).to_be_bytes())?;
             
             // Recursively write each element
@@ -221,8 +1123,96 @@ pub fn write_value<W: Write, V: MsgValue>(writer: &mut W, value: &V) -> std::io:
                 write_value(writer, &k.data)?;
                 write_value(writer, &v.data)?;
             }
+        },
+        // Extension
+        MsgPackValue::Ext(t, b) => {
+            // FixExt is used whenever the payload length allows it
+            let marker: u8 = value2marker(value.get_value());
+            writer.write_all(&[marker])?;
+            match marker {
+                markers::EXT8 => writer.write_all(&[b.len() as u8])?,
+                markers::EXT16 => writer.write_all(&(b.len() as u16).to_be_bytes())?,
+                markers::EXT32 => writer.write_all(&(b.len() as u32).to_be_bytes())?,
+                _ => {}
+            }
+            writer.write_all(&[*t as u8])?;
+            writer.write_all(b)?;
+        },
+        MsgPackValue::Timestamp { seconds, nanos } => {
+            // The smallest layout that can hold the value is chosen
+            match value2marker(value.get_value()) {
+                // Timestamp 32
+                markers::FIXEXT4 => {
+                    writer.write_all(&[markers::FIXEXT4, 0xFF])?;
+                    writer.write_all(&(*seconds as u32).to_be_bytes())?;
+                },
+                // Timestamp 64 - upper 30 bits represent the nanoseconds and lower 34 bits represent the seconds
+                markers::FIXEXT8 => {
+                    writer.write_all(&[markers::FIXEXT8, 0xFF])?;
+                    writer.write_all(&((u64::from(*nanos) << 34) | *seconds as u64).to_be_bytes())?;
+                },
+                // Timestamp 96
+                _ => {
+                    writer.write_all(&[markers::EXT8, 12, 0xFF])?;
+                    writer.write_all(&(*nanos).to_be_bytes())?;
+                    writer.write_all(&(*seconds).to_be_bytes())?;
+                }
+            }
         }
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp_nanos_out_of_range() {
+        for nanos in [1_000_000_000, (1 << 30) - 1, 1 << 30, u32::MAX] {
+            for seconds in [1, 1 << 33, -1] {
+                let value = MsgPackValue::Timestamp { seconds, nanos };
+                assert!(write_value(&mut vec![], &value).is_err(), "{} {}", seconds, nanos);
+                assert!(try_pack(&MsgPackEntry::from(value)).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn check_range_timestamp_nanos() {
+        let value = MsgPackValue::Timestamp { seconds: 0, nanos: 1_000_000_000 };
+        assert_eq!(
+            "Timestamp can't hold 1000000000 nanoseconds, the range is 0..=999999999", 
+            check_range(&value).unwrap_err().to_string()
+        );
+        assert!(check_range(&MsgPackValue::Timestamp { seconds: 0, nanos: 999_999_999 }).is_ok());
+    }
+
+    #[test]
+    fn timestamp_nanos_round_trip() {
+        for seconds in [1, 1 << 33, -1] {
+            let entry = MsgPackEntry::from(MsgPackValue::Timestamp { seconds, nanos: 999_999_999 });
+            assert_eq!(entry, unpack(&pack(&entry)).unwrap());
+        }
+    }
+
+    #[test]
+    fn overlong_fixstr_fails_to_pack() {
+        let mut entry = unpack(&[0xA1, 0x61]).unwrap();
+        entry.data = MsgPackValue::FixStr("a".repeat(32));
+
+        assert!(try_pack(&entry).is_err());
+        assert!(write_value(&mut vec![], &entry.data).is_err());
+        // Helpers that pick markers on their own get it right
+        assert_eq!(Some(32), unpack(&pack_minimal(&entry)).unwrap().data.as_str().map(str::len));
+        assert_eq!(pack_minimal(&entry), pack_canonical(&entry));
+    }
+
+    #[test]
+    #[should_panic]
+    fn overlong_fixstr_panics_in_pack() {
+        pack(&MsgPackEntry::from(MsgPackValue::FixStr("a".repeat(32))));
+    }
+}
@@ -0,0 +1,243 @@
+//! Input and output used by the decoder and the encoder
+//!
+//! It's `std::io` whenever the `std` feature is on,
+//!     otherwise it's a minimal stand-in that covers just what the crate needs on top of `alloc`
+
+#[cfg(feature = "std")]
+pub use std::io::{Cursor, Error, ErrorKind, Read, Result, Write};
+#[cfg(feature = "std")]
+pub use byteorder::{BigEndian, ReadBytesExt};
+
+#[cfg(not(feature = "std"))]
+pub use self::core_io::*;
+
+#[cfg(not(feature = "std"))]
+mod core_io {
+    use alloc::vec::Vec;
+
+    /// Kind of an IO error
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum ErrorKind {
+        UnexpectedEof,
+        Interrupted,
+        WriteZero,
+        InvalidData,
+        Other,
+    }
+
+    /// IO error
+    #[derive(Debug)]
+    pub struct Error {
+        kind: ErrorKind,
+    }
+    impl Error {
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+    impl From<ErrorKind> for Error {
+        fn from(kind: ErrorKind) -> Self {
+            Self { kind }
+        }
+    }
+    impl core::fmt::Display for Error {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "{:?}", self.kind)
+        }
+    }
+    impl core::error::Error for Error {}
+
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// Source of bytes
+    pub trait Read {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+        fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.read(buf)? {
+                    0 => return Err(ErrorKind::UnexpectedEof.into()),
+                    n => buf = &mut buf[n..],
+                }
+            }
+            Ok(())
+        }
+
+        fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+            let start: usize = buf.len();
+            let mut chunk = [0u8; 256];
+            loop {
+                match self.read(&mut chunk)? {
+                    0 => return Ok(buf.len() - start),
+                    n => buf.extend_from_slice(&chunk[..n]),
+                }
+            }
+        }
+
+        fn by_ref(&mut self) -> &mut Self where Self: Sized {
+            self
+        }
+
+        fn take(self, limit: u64) -> Take<Self> where Self: Sized {
+            Take { inner: self, limit }
+        }
+
+        fn chain<R: Read>(self, next: R) -> Chain<Self, R> where Self: Sized {
+            Chain { first: self, second: next, done_first: false }
+        }
+    }
+    impl<R: Read + ?Sized> Read for &mut R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+    }
+    impl Read for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let n: usize = buf.len().min(self.len());
+            buf[..n].copy_from_slice(&self[..n]);
+            *self = &self[n..];
+            Ok(n)
+        }
+    }
+
+    /// Reader limited to a number of bytes
+    pub struct Take<R> {
+        inner: R,
+        limit: u64,
+    }
+    impl<R: Read> Read for Take<R> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            if self.limit == 0 { return Ok(0); }
+            let max: usize = buf.len().min(usize::try_from(self.limit).unwrap_or(usize::MAX));
+            let n: usize = self.inner.read(&mut buf[..max])?;
+            self.limit -= n as u64;
+            Ok(n)
+        }
+    }
+
+    /// Two readers one after another
+    pub struct Chain<A, B> {
+        first: A,
+        second: B,
+        done_first: bool,
+    }
+    impl<A: Read, B: Read> Read for Chain<A, B> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            if !self.done_first {
+                match self.first.read(buf)? {
+                    0 if !buf.is_empty() => self.done_first = true,
+                    n => return Ok(n),
+                }
+            }
+            self.second.read(buf)
+        }
+    }
+
+    /// Reader over an in-memory buffer that tracks its position
+    #[derive(Clone, Debug, Default)]
+    pub struct Cursor<T> {
+        inner: T,
+        pos: u64,
+    }
+    impl<T> Cursor<T> {
+        pub fn new(inner: T) -> Self {
+            Self { inner, pos: 0 }
+        }
+
+        pub fn position(&self) -> u64 {
+            self.pos
+        }
+
+        pub fn set_position(&mut self, pos: u64) {
+            self.pos = pos;
+        }
+
+        pub fn get_ref(&self) -> &T {
+            &self.inner
+        }
+
+        pub fn into_inner(self) -> T {
+            self.inner
+        }
+    }
+    impl<T: AsRef<[u8]>> Read for Cursor<T> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let data: &[u8] = self.inner.as_ref();
+            let start: usize = usize::try_from(self.pos).unwrap_or(usize::MAX).min(data.len());
+            let n: usize = (&data[start..]).read(buf)?;
+            self.pos += n as u64;
+            Ok(n)
+        }
+    }
+
+    /// Sink of bytes
+    pub trait Write {
+        fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.write(buf)? {
+                    0 => return Err(ErrorKind::WriteZero.into()),
+                    n => buf = &buf[n..],
+                }
+            }
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            (**self).flush()
+        }
+    }
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    /// Byte order of multi-byte values
+    pub trait ByteOrder {}
+
+    /// MessagePack's byte order
+    pub enum BigEndian {}
+    impl ByteOrder for BigEndian {}
+
+    macro_rules! read_be {
+        ($($name:ident: $t:ty),*) => {$(
+            fn $name<B: ByteOrder>(&mut self) -> Result<$t> {
+                let mut buf = [0u8; core::mem::size_of::<$t>()];
+                self.read_exact(&mut buf)?;
+                Ok(<$t>::from_be_bytes(buf))
+            }
+        )*};
+    }
+
+    /// Reads big-endian numbers
+    pub trait ReadBytesExt: Read {
+        fn read_u8(&mut self) -> Result<u8> {
+            let mut buf = [0u8; 1];
+            self.read_exact(&mut buf)?;
+            Ok(buf[0])
+        }
+
+        fn read_i8(&mut self) -> Result<i8> {
+            self.read_u8().map(|b| b as i8)
+        }
+
+        read_be!(
+            read_u16: u16, read_u32: u32, read_u64: u64,
+            read_i16: i16, read_i32: i32, read_i64: i64,
+            read_f32: f32, read_f64: f64
+        );
+    }
+    impl<R: Read + ?Sized> ReadBytesExt for R {}
+}
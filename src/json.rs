@@ -0,0 +1,546 @@
+use crate::prelude::*;
+use serde_json::{Map, Number, Value};
+use serde::Deserialize;
+use crate::types::{MsgPackError, MsgPackEntry, MsgPackValue, BasicTypes};
+
+
+/// Standard base64 alphabet
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// What happens to maps holding keys that aren't strings when collapsing into plain JSON
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JsonMapKeys {
+    /// Such maps become arrays of `[key, value]` pairs, keys collapse like values do, 
+    ///     e.g. binary keys become `{"$bin": "<base64>"}` and invalid UTF-8 ones `{"$str_raw": "<base64>"}`
+    #[default]
+    Pairs,
+    /// Integer keys become their decimal strings, maps with any other non-string key become arrays of pairs
+    IntsAsStrings,
+    /// Such maps are an error naming the type of the first offending key
+    Strict,
+}
+
+impl MsgPackEntry {
+    /// Collapses the typed representation into plain JSON, see [`MsgPackValue::to_json_value`]
+    pub fn to_json_value(&self) -> Value {
+        self.data.to_json_value()
+    }
+
+    /// Collapses the typed representation into plain JSON with a policy for non-string map keys, 
+    ///     see [`MsgPackValue::to_json_value_with`]
+    pub fn to_json_value_with(&self, keys: JsonMapKeys) -> Result<Value, MsgPackError> {
+        self.data.to_json_value_with(keys)
+    }
+}
+
+impl MsgPackValue {
+    /// Collapses the typed representation into plain JSON
+    /// 
+    /// - Integers and floats become numbers, NaN and infinity become null
+    /// - Strings become strings
+    /// - Binary becomes `{"$bin": "<base64>"}` objects, so that it's never mistaken for a string
+    /// - Strings that aren't valid UTF-8 become `{"$str_raw": "<base64>"}` objects for the same reason
+    /// - Arrays become arrays
+    /// - Maps become objects when every key is a string,
+    ///   otherwise they become arrays of `[key, value]` pairs so that no key is lost
+    ///   (objects are keyed by string, so duplicate keys keep the last value),
+    ///   `to_json_value_with` picks another policy
+    /// - Extensions become `{"type": <type>, "data": "<base64>"}` objects
+    /// - Timestamps become `{"seconds": <seconds>, "nanos": <nanos>}` objects
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// let input = vec![0x82, 0xA1, 0x61, 0x01, 0xA1, 0x62, 0xC4, 0x02, 0xDE, 0xAD];
+    /// let value = rmpp::unpack(&input).unwrap();
+    /// 
+    /// assert_eq!(r###"{"a":1,"b":{"$bin":"3q0="}}"###, value.to_json_value().to_string());
+    /// 
+    /// let input = vec![0x81, 0x01, 0xC3];
+    /// let value = rmpp::unpack(&input).unwrap();
+    /// 
+    /// assert_eq!("[[1,true]]", value.to_json_value().to_string());
+    /// 
+    /// let input = vec![0xA2, 0xC3, 0x28];
+    /// let value = rmpp::unpack_raw_str(&input).unwrap();
+    /// 
+    /// assert_eq!(r###"{"$str_raw":"wyg="}"###, value.to_json_value().to_string());
+    /// ```
+    pub fn to_json_value(&self) -> Value {
+        self.to_json_value_with(JsonMapKeys::Pairs).unwrap() // Only the strict policy fails
+    }
+
+    /// Collapses the typed representation into plain JSON like `to_json_value`, 
+    ///     except that maps with keys that aren't strings are handled according to the policy
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use rmpp::json::JsonMapKeys;
+    /// 
+    /// let input = vec![0x82, 0x01, 0xC3, 0xA1, 0x62, 0xC2];
+    /// let value = rmpp::unpack(&input).unwrap();
+    /// 
+    /// assert_eq!("[[1,true],[\"b\",false]]", value.to_json_value_with(JsonMapKeys::Pairs).unwrap().to_string());
+    /// assert_eq!(r###"{"1":true,"b":false}"###, value.to_json_value_with(JsonMapKeys::IntsAsStrings).unwrap().to_string());
+    /// assert_eq!(
+    ///     "Map key of type FixPos can't become a JSON object key", 
+    ///     value.to_json_value_with(JsonMapKeys::Strict).unwrap_err().to_string()
+    /// );
+    /// ```
+    pub fn to_json_value_with(&self, keys: JsonMapKeys) -> Result<Value, MsgPackError> {
+        let value: Value = match self {
+            // Null
+            MsgPackValue::Null => Value::Null,
+            // Boolean
+            MsgPackValue::Bool(b) => Value::Bool(*b),
+            // Integer
+            MsgPackValue::FixPos(n) => Value::from(*n),
+            MsgPackValue::FixNeg(n) => Value::from(*n),
+            MsgPackValue::U8(n)  => Value::from(*n),
+            MsgPackValue::U16(n) => Value::from(*n),
+            MsgPackValue::U32(n) => Value::from(*n),
+            MsgPackValue::U64(n) => Value::from(*n),
+            MsgPackValue::I8(n)  => Value::from(*n),
+            MsgPackValue::I16(n) => Value::from(*n),
+            MsgPackValue::I32(n) => Value::from(*n),
+            MsgPackValue::I64(n) => Value::from(*n),
+            // Float - JSON has no way to represent NaN and infinity
+            MsgPackValue::F32(n) => Number::from_f64(f64::from(*n)).map_or(Value::Null, Value::Number),
+            MsgPackValue::F64(n) => Number::from_f64(*n).map_or(Value::Null, Value::Number),
+            // String
+            MsgPackValue::FixStr(s)|MsgPackValue::Str8(s)|
+            MsgPackValue::Str16(s)|MsgPackValue::Str32(s) => Value::String(s.clone()),
+            MsgPackValue::StrRaw(b) => {
+                let mut object = Map::new();
+                object.insert("$str_raw".to_string(), Value::String(to_base64(b)));
+                Value::Object(object)
+            },
+            // Binary
+            MsgPackValue::Bin8(b)|MsgPackValue::Bin16(b)|MsgPackValue::Bin32(b) => {
+                let mut object = Map::new();
+                object.insert("$bin".to_string(), Value::String(to_base64(b)));
+                Value::Object(object)
+            },
+            // Array
+            MsgPackValue::FixArray(values)|MsgPackValue::Array16(values)|MsgPackValue::Array32(values) => {
+                Value::Array(values.iter().map(|v| v.to_json_value_with(keys)).collect::<Result<_, _>>()?)
+            },
+            // Map
+            MsgPackValue::FixMap(values)|MsgPackValue::Map16(values)|MsgPackValue::Map32(values) => {
+                map_to_json_value(values, keys)?
+            },
+            // Extension
+            MsgPackValue::Ext(t, b) => {
+                let mut object = Map::new();
+                object.insert("type".to_string(), Value::from(*t));
+                object.insert("data".to_string(), Value::String(to_base64(b)));
+                Value::Object(object)
+            },
+            MsgPackValue::Timestamp { seconds, nanos } => {
+                let mut object = Map::new();
+                object.insert("seconds".to_string(), Value::from(*seconds));
+                object.insert("nanos".to_string(), Value::from(*nanos));
+                Value::Object(object)
+            }
+        };
+        Ok(value)
+    }
+}
+
+impl From<&MsgPackEntry> for Value {
+    fn from(entry: &MsgPackEntry) -> Self {
+        entry.to_json_value()
+    }
+}
+
+impl From<&MsgPackValue> for Value {
+    fn from(value: &MsgPackValue) -> Self {
+        value.to_json_value()
+    }
+}
+
+/// Collapses map entries into a JSON object, or handles keys that aren't strings according to the policy
+fn map_to_json_value(values: &[(MsgPackEntry, MsgPackEntry)], policy: JsonMapKeys) -> Result<Value, MsgPackError> {
+    let key_string = |k: &MsgPackEntry| match (k.data.as_str(), k.data.as_int()) {
+        (Some(s), _) => Some(s.to_string()),
+        (None, Some(n)) if policy == JsonMapKeys::IntsAsStrings => Some(n.to_string()),
+        _ => None
+    };
+    let keys: Option<Vec<String>> = values.iter().map(|(k, _)| key_string(k)).collect();
+
+    match keys {
+        Some(keys) => {
+            let mut object = Map::new();
+            for (k, (_, v)) in keys.into_iter().zip(values) {
+                object.insert(k, v.to_json_value_with(policy)?);
+            }
+            Ok(Value::Object(object))
+        },
+        None if policy == JsonMapKeys::Strict => {
+            let (k, _) = values.iter().find(|(k, _)| k.data.as_str().is_none()).unwrap();
+            Err(MsgPackError::Custom(format!("Map key of type {} can't become a JSON object key", k.data.marker_name())))
+        },
+        None => {
+            let pairs = values.iter().map(|(k, v)| {
+                Ok(Value::Array(vec![k.to_json_value_with(policy)?, v.to_json_value_with(policy)?]))
+            });
+            Ok(Value::Array(pairs.collect::<Result<_, MsgPackError>>()?))
+        }
+    }
+}
+
+/// Encodes bytes as a padded standard base64 string
+pub(crate) fn to_base64(bytes: &[u8]) -> String {
+    let mut res = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        // Every 3 bytes are split into 4 groups of 6 bits
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                res.push(BASE64_ALPHABET[((n >> (18 - i * 6)) & 0b0011_1111) as usize] as char);
+            } else {
+                res.push('='); // Padding
+            }
+        }
+    }
+
+    res
+}
+
+/// Replaces the byte arrays of binary values inside of a json-encoded MsgPackEntry with hex strings
+pub(crate) fn bin_to_hex(json: &mut Value) {
+    match json {
+        Value::Object(object) => {
+            let is_bin: bool = matches!(object.get("type").and_then(Value::as_str), Some("Bin8"|"Bin16"|"Bin32"));
+            for (k, v) in object.iter_mut() {
+                match v {
+                    Value::Array(bytes) if is_bin && k == "value" => {
+                        let bytes: Vec<u8> = bytes.iter().filter_map(Value::as_u64).map(|b| b as u8).collect();
+                        *v = Value::String(to_hex(&bytes));
+                    },
+                    _ => bin_to_hex(v)
+                }
+            }
+        },
+        Value::Array(values) => values.iter_mut().for_each(bin_to_hex),
+        _ => {}
+    }
+}
+
+/// Cuts strings and byte arrays inside of a json-encoded MsgPackEntry down to `max_len` bytes, 
+///     noting how many bytes were left out
+/// 
+/// Only what's found under `value` keys is cut, so the field names and type names stay intact
+pub(crate) fn truncate_fields(json: &mut Value, max_len: usize, is_value: bool) {
+    match json {
+        Value::String(s) if is_value && s.len() > max_len => {
+            let mut end: usize = max_len;
+            while !s.is_char_boundary(end) { end -= 1; }
+            let more: usize = s.len() - end;
+
+            s.truncate(end);
+            s.push_str(&format!("...({} more)", more));
+        },
+        // Only binary and extension data are arrays of plain numbers
+        Value::Array(values) if is_value && values.len() > max_len && values.iter().all(Value::is_number) => {
+            let more: usize = values.len() - max_len;
+
+            values.truncate(max_len);
+            values.push(Value::String(format!("...({} more)", more)));
+        },
+        Value::Object(object) => object.iter_mut().for_each(|(k, v)| truncate_fields(v, max_len, k == "value")),
+        Value::Array(values) => values.iter_mut().for_each(|v| truncate_fields(v, max_len, is_value)),
+        _ => {}
+    }
+}
+
+/// Serializes a value into a json string pretty-printed with the given indent
+#[cfg(feature = "std")]
+pub(crate) fn to_json_string_indent<T: serde::Serialize + ?Sized>(value: &T, indent: &str) -> Result<String, serde_json::Error> {
+    let mut buf: Vec<u8> = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    value.serialize(&mut serde_json::Serializer::with_formatter(&mut buf, formatter))?;
+    Ok(String::from_utf8(buf).unwrap()) // serde_json only writes UTF-8 as long as the indent is UTF-8
+}
+
+/// Encodes bytes as a lowercase hex string
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes a hex string of either case
+pub(crate) fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() { return None; }
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok()).collect()
+}
+
+/// Decodes a padded or unpadded standard base64 string
+pub(crate) fn from_base64(s: &str) -> Option<Vec<u8>> {
+    let s: &str = s.trim_end_matches('=');
+    let mut res: Vec<u8> = Vec::with_capacity(s.len() * 3 / 4);
+
+    for chunk in s.as_bytes().chunks(4) {
+        // A single leftover character can't form a byte
+        if chunk.len() == 1 { return None; }
+
+        // Every 4 groups of 6 bits are joined into 3 bytes
+        let mut n: u32 = 0;
+        for (i, c) in chunk.iter().enumerate() {
+            let bits = BASE64_ALPHABET.iter().position(|a| a == c)? as u32;
+            n |= bits << (18 - i * 6);
+        }
+
+        let bytes = n.to_be_bytes();
+        res.extend_from_slice(&bytes[1..chunk.len()]);
+    }
+
+    Some(res)
+}
+
+/// Checks that a json value has the shape of a json-encoded MsgPackEntry,
+///     describing the first mismatch along with its path
+pub(crate) fn validate_entry(json: &Value, path: &str) -> Result<(), String> {
+    let entry: &Map<String, Value> = json.as_object()
+        .ok_or_else(|| path_error(path, "expected an entry object"))?;
+
+    if entry.get("raw_marker").and_then(Value::as_u64).is_none_or(|m| m > 0xFF) {
+        return Err(path_error(&field_path(path, "raw_marker"), "expected a marker byte"));
+    }
+
+    if !entry.get("basic_type").is_some_and(|t| t.is_string() && BasicTypes::deserialize(t).is_ok()) {
+        return Err(path_error(&field_path(path, "basic_type"), "expected one of the basic type names"));
+    }
+
+    let data_path: String = field_path(path, "data");
+    let data: &Map<String, Value> = entry.get("data").and_then(Value::as_object)
+        .ok_or_else(|| path_error(&data_path, "expected a value object"))?;
+
+    validate_value(data, &data_path)
+}
+
+/// Checks that a json value has the shape of a json-encoded MsgPackValue
+fn validate_value(data: &Map<String, Value>, data_path: &str) -> Result<(), String> {
+    let type_path: String = field_path(data_path, "type");
+    let value_type: &str = data.get("type").and_then(Value::as_str)
+        .ok_or_else(|| path_error(&type_path, "expected a type name"))?;
+    let value: Option<&Value> = data.get("value");
+    let path: &str = &field_path(data_path, "value");
+
+    let is_bytes = |v: &Value| v.as_array().is_some_and(|b| b.iter().all(|b| b.as_u64().is_some_and(|b| b <= 0xFF)));
+
+    let (valid, expected): (bool, &str) = match value_type {
+        "Null" => (value.is_none_or(Value::is_null), "no value"),
+        "Bool" => (value.is_some_and(Value::is_boolean), "a boolean"),
+        "FixPos"|"FixNeg"|"U8"|"U16"|"U32"|"U64"|"I8"|"I16"|"I32"|"I64" => {
+            let n: Option<i128> = value.and_then(|v| v.as_i64().map(i128::from).or(v.as_u64().map(i128::from)));
+            let Some(n) = n else {
+                return Err(path_error(path, format!("expected an integer for type {}", value_type)));
+            };
+
+            let (min, max): (i128, i128) = int_range(value_type);
+            if !(min..=max).contains(&n) {
+                return Err(path_error(path, format!("{} is out of range {}..={} for type {}", n, min, max, value_type)));
+            }
+            return Ok(());
+        },
+        "F32"|"F64" => {
+            let is_sentinel = |v: &Value| matches!(v.as_str(), Some("NaN"|"Infinity"|"-Infinity"));
+            (value.is_some_and(|v| v.is_number() || is_sentinel(v)), "a number or a float sentinel")
+        },
+        "FixStr"|"Str8"|"Str16"|"Str32" => (value.is_some_and(Value::is_string), "a string"),
+        "StrRaw" => (value.and_then(Value::as_str).is_some_and(|s| from_base64(s).is_some()), "a base64 string"),
+        "Bin8"|"Bin16"|"Bin32" => {
+            let is_hex = |v: &Value| v.as_str().is_some_and(|s| from_hex(s).is_some());
+            (value.is_some_and(|v| is_bytes(v) || is_hex(v)), "an array of bytes or a hex string")
+        },
+        "FixArray"|"Array16"|"Array32" => {
+            let Some(values) = value.and_then(Value::as_array) else {
+                return Err(path_error(path, format!("expected an array of entries for type {}", value_type)));
+            };
+            return values.iter().enumerate().try_for_each(|(i, v)| validate_entry(v, &format!("{}[{}]", path, i)));
+        },
+        "FixMap"|"Map16"|"Map32" => {
+            let Some(pairs) = value.and_then(Value::as_array) else {
+                return Err(path_error(path, format!("expected an array of [key, value] pairs for type {}", value_type)));
+            };
+            for (i, pair) in pairs.iter().enumerate() {
+                let pair_path: String = format!("{}[{}]", path, i);
+                match pair.as_array().map(Vec::as_slice) {
+                    Some([k, v]) => {
+                        validate_entry(k, &format!("{}[0]", pair_path))?;
+                        validate_entry(v, &format!("{}[1]", pair_path))?;
+                    },
+                    _ => return Err(path_error(&pair_path, "expected a [key, value] pair"))
+                }
+            }
+            return Ok(());
+        },
+        "Ext" => {
+            let is_ext = |v: &Value| match v.as_array().map(Vec::as_slice) {
+                Some([t, b]) => t.as_i64().is_some_and(|t| i8::try_from(t).is_ok()) && is_bytes(b),
+                _ => false
+            };
+            (value.is_some_and(is_ext), "an [extension type, bytes] pair")
+        },
+        "Timestamp" => {
+            if let Some(nanos) = value.and_then(|v| v.get("nanos")).and_then(Value::as_u64) && nanos > 999_999_999 {
+                return Err(path_error(&field_path(path, "nanos"), "expected 0..=999999999"));
+            }
+            let is_timestamp = |v: &Value| v.get("seconds").is_some_and(Value::is_i64) && v.get("nanos").is_some_and(Value::is_u64);
+            (value.is_some_and(is_timestamp), "an object of seconds and nanos")
+        },
+        _ => return Err(path_error(&type_path, format!("unknown type {}", value_type)))
+    };
+
+    if valid { Ok(()) } else { Err(path_error(path, format!("expected {} for type {}", expected, value_type))) }
+}
+
+/// Returns the range of values an integer type holds
+fn int_range(value_type: &str) -> (i128, i128) {
+    match value_type {
+        "FixPos" => (0, 0x7F),
+        "FixNeg" => (-32, -1),
+        "U8" => (0, u8::MAX.into()),
+        "U16" => (0, u16::MAX.into()),
+        "U32" => (0, u32::MAX.into()),
+        "U64" => (0, u64::MAX.into()),
+        "I8" => (i8::MIN.into(), i8::MAX.into()),
+        "I16" => (i16::MIN.into(), i16::MAX.into()),
+        "I32" => (i32::MIN.into(), i32::MAX.into()),
+        _ => (i64::MIN.into(), i64::MAX.into()),
+    }
+}
+
+/// Appends a field to a dotted path
+fn field_path(path: &str, field: &str) -> String {
+    if path.is_empty() { field.to_string() } else { format!("{}.{}", path, field) }
+}
+
+/// Prefixes a message with the path it refers to
+fn path_error<M: core::fmt::Display>(path: &str, msg: M) -> String {
+    if path.is_empty() { msg.to_string() } else { format!("{}: {}", path, msg) }
+}
+
+/// Serde helpers representing bytes as a base64 string
+pub(crate) mod base64 {
+    use crate::prelude::*;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::to_base64(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s: String = String::deserialize(deserializer)?;
+        super::from_base64(&s).ok_or_else(|| D::Error::custom("Invalid base64"))
+    }
+}
+
+/// Serde helpers reading bytes from either an array of bytes or a hex string
+pub(crate) mod bytes {
+    use crate::prelude::*;
+    use serde::{Deserialize, Deserializer, de::Error};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Bytes {
+        Array(Vec<u8>),
+        Hex(String),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        match Bytes::deserialize(deserializer)? {
+            Bytes::Array(b) => Ok(b),
+            Bytes::Hex(s) => super::from_hex(&s).ok_or_else(|| D::Error::custom("Invalid hex"))
+        }
+    }
+}
+
+/// Returns the string sentinel representing a float JSON has no way to represent
+fn float_sentinel(n: f64) -> Option<&'static str> {
+    if n.is_nan() { Some("NaN") }
+    else if n == f64::INFINITY { Some("Infinity") }
+    else if n == f64::NEG_INFINITY { Some("-Infinity") }
+    else { None }
+}
+
+/// Reads a float from either a number or a string sentinel
+struct FloatVisitor;
+impl serde::de::Visitor<'_> for FloatVisitor {
+    type Value = f64;
+
+    fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "a number or one of \"NaN\", \"Infinity\", \"-Infinity\"")
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<f64, E> { Ok(v) }
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<f64, E> { Ok(v as f64) }
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<f64, E> { Ok(v as f64) }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<f64, E> {
+        match v {
+            "NaN" => Ok(f64::NAN),
+            "Infinity" => Ok(f64::INFINITY),
+            "-Infinity" => Ok(f64::NEG_INFINITY),
+            _ => Err(E::invalid_value(serde::de::Unexpected::Str(v), &self))
+        }
+    }
+}
+
+/// Serde helpers representing NaN and infinity of an f32 as string sentinels
+pub(crate) mod float32 {
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(n: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+        match super::float_sentinel(f64::from(*n)) {
+            Some(s) => serializer.serialize_str(s),
+            None => serializer.serialize_f32(*n)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+        deserializer.deserialize_any(super::FloatVisitor).map(|n| n as f32)
+    }
+}
+
+/// Serde helpers representing NaN and infinity of an f64 as string sentinels
+pub(crate) mod float64 {
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(n: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        match super::float_sentinel(*n) {
+            Some(s) => serializer.serialize_str(s),
+            None => serializer.serialize_f64(*n)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        deserializer.deserialize_any(super::FloatVisitor)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::encode::validate_json;
+
+    #[test]
+    fn timestamp_nanos_out_of_range() {
+        let json = |nanos: u64| format!(
+            r###"{{"raw_marker":215,"basic_type":"Ext","data":{{"type":"Timestamp","value":{{"seconds":1,"nanos":{}}}}}}}"###, nanos
+        );
+
+        assert!(validate_json(&json(999_999_999)).is_ok());
+        for nanos in [1_000_000_000, 1 << 30, u64::from(u32::MAX)] {
+            assert_eq!(
+                "data.value.nanos: expected 0..=999999999", 
+                validate_json(&json(nanos)).unwrap_err().to_string()
+            );
+        }
+    }
+}
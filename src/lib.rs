@@ -1,9 +1,34 @@
 #![doc = include_str!("../README.md")]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+pub mod io;
 pub mod types;
 pub mod decode;
 pub mod encode;
+pub mod json;
+pub mod builder;
+pub mod ser;
+pub mod de;
+pub mod borrowed;
+pub mod markers;
 
 pub use types::*;
 pub use decode::*;
-pub use encode::*;
\ No newline at end of file
+pub use encode::*;
+pub use builder::*;
+pub use ser::*;
+pub use de::*;
+pub use borrowed::*;
+
+/// Names that `std` brings into scope by itself
+#[allow(unused_imports)]
+mod prelude {
+    pub(crate) use alloc::borrow::ToOwned;
+    pub(crate) use alloc::boxed::Box;
+    pub(crate) use alloc::format;
+    pub(crate) use alloc::string::{String, ToString};
+    pub(crate) use alloc::vec;
+    pub(crate) use alloc::vec::Vec;
+}
\ No newline at end of file
@@ -0,0 +1,100 @@
+//! Marker bytes of the MessagePack format
+//!
+//! Fixed-size families hold their value or length in the lower bits,
+//!     their constants are the first byte of the family.
+//! The crate's own code refers to markers through these constants (or `rmp::Marker`), 
+//!     while the buffers in examples and tests stay raw bytes, the way they appear on the wire
+//!
+//! # Examples
+//!
+//! ```
+//! use rmpp::markers;
+//!
+//! assert_eq!(vec![markers::TRUE], rmpp::pack(&rmpp::msgpack!(true)));
+//! assert_eq!(vec![markers::FIXARRAY | 1, markers::NULL], rmpp::pack(&rmpp::msgpack!([null])));
+//! ```
+
+/// Positive fixint, 0x00..=0x7F
+pub const FIXPOS: u8 = 0x00;
+/// Fixmap, 0x80..=0x8F
+pub const FIXMAP: u8 = 0x80;
+/// Fixarray, 0x90..=0x9F
+pub const FIXARRAY: u8 = 0x90;
+/// Fixstr, 0xA0..=0xBF
+pub const FIXSTR: u8 = 0xA0;
+/// Negative fixint, 0xE0..=0xFF
+pub const FIXNEG: u8 = 0xE0;
+
+/// Nil
+pub const NULL: u8 = 0xC0;
+/// Never used by the format
+pub const RESERVED: u8 = 0xC1;
+/// False
+pub const FALSE: u8 = 0xC2;
+/// True
+pub const TRUE: u8 = 0xC3;
+
+/// Binary with an 8-bit length
+pub const BIN8: u8 = 0xC4;
+/// Binary with a 16-bit length
+pub const BIN16: u8 = 0xC5;
+/// Binary with a 32-bit length
+pub const BIN32: u8 = 0xC6;
+
+/// Extension with an 8-bit length
+pub const EXT8: u8 = 0xC7;
+/// Extension with a 16-bit length
+pub const EXT16: u8 = 0xC8;
+/// Extension with a 32-bit length
+pub const EXT32: u8 = 0xC9;
+
+/// 32-bit float
+pub const F32: u8 = 0xCA;
+/// 64-bit float
+pub const F64: u8 = 0xCB;
+
+/// 8-bit unsigned integer
+pub const U8: u8 = 0xCC;
+/// 16-bit unsigned integer
+pub const U16: u8 = 0xCD;
+/// 32-bit unsigned integer
+pub const U32: u8 = 0xCE;
+/// 64-bit unsigned integer
+pub const U64: u8 = 0xCF;
+
+/// 8-bit signed integer
+pub const I8: u8 = 0xD0;
+/// 16-bit signed integer
+pub const I16: u8 = 0xD1;
+/// 32-bit signed integer
+pub const I32: u8 = 0xD2;
+/// 64-bit signed integer
+pub const I64: u8 = 0xD3;
+
+/// Extension with 1 byte of data
+pub const FIXEXT1: u8 = 0xD4;
+/// Extension with 2 bytes of data
+pub const FIXEXT2: u8 = 0xD5;
+/// Extension with 4 bytes of data
+pub const FIXEXT4: u8 = 0xD6;
+/// Extension with 8 bytes of data
+pub const FIXEXT8: u8 = 0xD7;
+/// Extension with 16 bytes of data
+pub const FIXEXT16: u8 = 0xD8;
+
+/// String with an 8-bit length
+pub const STR8: u8 = 0xD9;
+/// String with a 16-bit length
+pub const STR16: u8 = 0xDA;
+/// String with a 32-bit length
+pub const STR32: u8 = 0xDB;
+
+/// Array with a 16-bit length
+pub const ARRAY16: u8 = 0xDC;
+/// Array with a 32-bit length
+pub const ARRAY32: u8 = 0xDD;
+
+/// Map with a 16-bit length
+pub const MAP16: u8 = 0xDE;
+/// Map with a 32-bit length
+pub const MAP32: u8 = 0xDF;
@@ -0,0 +1,235 @@
+use crate::prelude::*;
+use serde::ser::{self, Serialize};
+use crate::types::{MsgPackError, MsgPackEntry, MsgPackValue};
+use crate::types::{uint2value, int2value, str2value, bin2value, array2value, map2value};
+
+
+/// Turns any serializable Rust value into a MsgPackEntry object, picking the narrowest marker for every value
+/// 
+/// - Structs and maps become maps, sequences and tuples become arrays
+/// - Unit values and None become Null
+/// - Unit enum variants become strings,
+///   other variants become single-entry maps of `{variant: content}`
+/// 
+/// # Examples
+/// 
+/// ```
+/// #[derive(serde::Serialize)]
+/// struct Point { x: i32, y: f64 }
+/// 
+/// let entry = rmpp::to_entry(&Point { x: 1, y: 0.5 }).unwrap();
+/// 
+/// assert_eq!(rmpp::MsgPackValue::FixPos(1), entry.data.get("x").unwrap().data);
+/// assert_eq!(rmpp::MsgPackValue::F64(0.5), entry.data.get("y").unwrap().data);
+/// ```
+pub fn to_entry<T: Serialize + ?Sized>(value: &T) -> Result<MsgPackEntry, MsgPackError> {
+    value.serialize(EntrySerializer)
+}
+
+impl ser::Error for MsgPackError {
+    fn custom<T: core::fmt::Display>(msg: T) -> Self {
+        MsgPackError::Custom(msg.to_string())
+    }
+}
+
+/// Wraps the content of an enum variant into a single-entry map
+fn variant_entry(variant: &'static str, value: MsgPackEntry) -> MsgPackEntry {
+    MsgPackEntry::from(map2value(vec![(MsgPackEntry::from(str2value(variant.to_string())), value)]))
+}
+
+/// Serializes Rust values into MsgPackEntry objects
+struct EntrySerializer;
+
+impl ser::Serializer for EntrySerializer {
+    type Ok = MsgPackEntry;
+    type Error = MsgPackError;
+
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    // Boolean
+    fn serialize_bool(self, v: bool) -> Result<MsgPackEntry, MsgPackError> { Ok(MsgPackValue::Bool(v).into()) }
+    // Integer
+    fn serialize_i8(self, v: i8) -> Result<MsgPackEntry, MsgPackError> { self.serialize_i64(i64::from(v)) }
+    fn serialize_i16(self, v: i16) -> Result<MsgPackEntry, MsgPackError> { self.serialize_i64(i64::from(v)) }
+    fn serialize_i32(self, v: i32) -> Result<MsgPackEntry, MsgPackError> { self.serialize_i64(i64::from(v)) }
+    fn serialize_i64(self, v: i64) -> Result<MsgPackEntry, MsgPackError> { Ok(int2value(v).into()) }
+    fn serialize_i128(self, v: i128) -> Result<MsgPackEntry, MsgPackError> {
+        match (i64::try_from(v), u64::try_from(v)) {
+            (Ok(n), _) => self.serialize_i64(n),
+            (_, Ok(n)) => self.serialize_u64(n),
+            _ => Err(MsgPackError::Custom(format!("Integer {} doesn't fit into 64 bits", v)))
+        }
+    }
+    fn serialize_u8(self, v: u8) -> Result<MsgPackEntry, MsgPackError> { self.serialize_u64(u64::from(v)) }
+    fn serialize_u16(self, v: u16) -> Result<MsgPackEntry, MsgPackError> { self.serialize_u64(u64::from(v)) }
+    fn serialize_u32(self, v: u32) -> Result<MsgPackEntry, MsgPackError> { self.serialize_u64(u64::from(v)) }
+    fn serialize_u64(self, v: u64) -> Result<MsgPackEntry, MsgPackError> { Ok(uint2value(v).into()) }
+    fn serialize_u128(self, v: u128) -> Result<MsgPackEntry, MsgPackError> {
+        u64::try_from(v)
+            .map_err(|_| MsgPackError::Custom(format!("Integer {} doesn't fit into 64 bits", v)))
+            .and_then(|n| self.serialize_u64(n))
+    }
+    // Float
+    fn serialize_f32(self, v: f32) -> Result<MsgPackEntry, MsgPackError> { Ok(MsgPackValue::F32(v).into()) }
+    fn serialize_f64(self, v: f64) -> Result<MsgPackEntry, MsgPackError> { Ok(MsgPackValue::F64(v).into()) }
+    // String
+    fn serialize_char(self, v: char) -> Result<MsgPackEntry, MsgPackError> { self.serialize_str(v.encode_utf8(&mut [0u8; 4])) }
+    fn serialize_str(self, v: &str) -> Result<MsgPackEntry, MsgPackError> { Ok(str2value(v.to_string()).into()) }
+    // Binary
+    fn serialize_bytes(self, v: &[u8]) -> Result<MsgPackEntry, MsgPackError> { Ok(bin2value(v.to_vec()).into()) }
+    // Null
+    fn serialize_none(self) -> Result<MsgPackEntry, MsgPackError> { self.serialize_unit() }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<MsgPackEntry, MsgPackError> { value.serialize(self) }
+    fn serialize_unit(self) -> Result<MsgPackEntry, MsgPackError> { Ok(MsgPackValue::Null.into()) }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<MsgPackEntry, MsgPackError> { self.serialize_unit() }
+    // Enum variant and newtype
+    fn serialize_unit_variant(
+        self, _name: &'static str, _index: u32, variant: &'static str
+    ) -> Result<MsgPackEntry, MsgPackError> {
+        self.serialize_str(variant)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self, _name: &'static str, value: &T
+    ) -> Result<MsgPackEntry, MsgPackError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self, _name: &'static str, _index: u32, variant: &'static str, value: &T
+    ) -> Result<MsgPackEntry, MsgPackError> {
+        Ok(variant_entry(variant, value.serialize(EntrySerializer)?))
+    }
+    // Array
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, MsgPackError> {
+        Ok(SeqSerializer::new(len.unwrap_or(0), None))
+    }
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, MsgPackError> {
+        Ok(SeqSerializer::new(len, None))
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqSerializer, MsgPackError> {
+        Ok(SeqSerializer::new(len, None))
+    }
+    fn serialize_tuple_variant(
+        self, _name: &'static str, _index: u32, variant: &'static str, len: usize
+    ) -> Result<SeqSerializer, MsgPackError> {
+        Ok(SeqSerializer::new(len, Some(variant)))
+    }
+    // Map
+    fn serialize_map(self, len: Option<usize>) -> Result<MapSerializer, MsgPackError> {
+        Ok(MapSerializer::new(len.unwrap_or(0), None))
+    }
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<MapSerializer, MsgPackError> {
+        Ok(MapSerializer::new(len, None))
+    }
+    fn serialize_struct_variant(
+        self, _name: &'static str, _index: u32, variant: &'static str, len: usize
+    ) -> Result<MapSerializer, MsgPackError> {
+        Ok(MapSerializer::new(len, Some(variant)))
+    }
+}
+
+/// Collects array elements
+struct SeqSerializer {
+    values: Vec<MsgPackEntry>,
+    /// Enum variant the array belongs to, if any
+    variant: Option<&'static str>,
+}
+impl SeqSerializer {
+    fn new(len: usize, variant: Option<&'static str>) -> Self {
+        Self { values: Vec::with_capacity(len), variant }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MsgPackError> {
+        self.values.push(value.serialize(EntrySerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<MsgPackEntry, MsgPackError> {
+        let entry = MsgPackEntry::from(array2value(self.values));
+        Ok(match self.variant { Some(variant) => variant_entry(variant, entry), None => entry })
+    }
+}
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = MsgPackEntry;
+    type Error = MsgPackError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MsgPackError> { self.push(value) }
+    fn end(self) -> Result<MsgPackEntry, MsgPackError> { self.finish() }
+}
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = MsgPackEntry;
+    type Error = MsgPackError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MsgPackError> { self.push(value) }
+    fn end(self) -> Result<MsgPackEntry, MsgPackError> { self.finish() }
+}
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = MsgPackEntry;
+    type Error = MsgPackError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MsgPackError> { self.push(value) }
+    fn end(self) -> Result<MsgPackEntry, MsgPackError> { self.finish() }
+}
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = MsgPackEntry;
+    type Error = MsgPackError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MsgPackError> { self.push(value) }
+    fn end(self) -> Result<MsgPackEntry, MsgPackError> { self.finish() }
+}
+
+/// Collects map key-value pairs
+struct MapSerializer {
+    pairs: Vec<(MsgPackEntry, MsgPackEntry)>,
+    /// Key waiting for its value
+    key: Option<MsgPackEntry>,
+    /// Enum variant the map belongs to, if any
+    variant: Option<&'static str>,
+}
+impl MapSerializer {
+    fn new(len: usize, variant: Option<&'static str>) -> Self {
+        Self { pairs: Vec::with_capacity(len), key: None, variant }
+    }
+
+    fn push_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), MsgPackError> {
+        self.pairs.push((MsgPackEntry::from(str2value(key.to_string())), value.serialize(EntrySerializer)?));
+        Ok(())
+    }
+
+    fn finish(self) -> Result<MsgPackEntry, MsgPackError> {
+        let entry = MsgPackEntry::from(map2value(self.pairs));
+        Ok(match self.variant { Some(variant) => variant_entry(variant, entry), None => entry })
+    }
+}
+impl ser::SerializeMap for MapSerializer {
+    type Ok = MsgPackEntry;
+    type Error = MsgPackError;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), MsgPackError> {
+        self.key = Some(key.serialize(EntrySerializer)?);
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MsgPackError> {
+        let key: MsgPackEntry = self.key.take()
+            .ok_or_else(|| MsgPackError::Custom("Map value without a key".to_string()))?;
+        self.pairs.push((key, value.serialize(EntrySerializer)?));
+        Ok(())
+    }
+    fn end(self) -> Result<MsgPackEntry, MsgPackError> { self.finish() }
+}
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = MsgPackEntry;
+    type Error = MsgPackError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), MsgPackError> {
+        self.push_field(key, value)
+    }
+    fn end(self) -> Result<MsgPackEntry, MsgPackError> { self.finish() }
+}
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = MsgPackEntry;
+    type Error = MsgPackError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), MsgPackError> {
+        self.push_field(key, value)
+    }
+    fn end(self) -> Result<MsgPackEntry, MsgPackError> { self.finish() }
+}
\ No newline at end of file
@@ -1,4 +1,12 @@
-use std::io;
+use crate::prelude::*;
+use crate::io;
+use crate::json::to_hex;
+use crate::markers;
+use alloc::collections::BTreeMap;
+use core::cmp::Ordering;
+use core::ops::RangeInclusive;
+use core::hash::{Hash, Hasher};
+use core::fmt::Write;
 use serde::{Deserialize, Serialize};
 
 
@@ -8,7 +16,19 @@ pub trait MsgValue {
 }
 
 /// This is the main type representing a MessagePack entry
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// 
+/// Deserializing fails when `basic_type` doesn't match the type of `data`
+/// 
+/// # Examples 
+/// 
+/// ```
+/// let json = r###"{"raw_marker":204,"basic_type":"String","data":{"type":"U8","value":1}}"###;
+/// let err = serde_json::from_str::<rmpp::MsgPackEntry>(json).unwrap_err();
+/// 
+/// assert!(err.to_string().starts_with("basic_type String doesn't match data of type U8, expected Number"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedEntry")]
 pub struct MsgPackEntry {
     /// A raw marker value
     pub raw_marker: u8,
@@ -17,10 +37,425 @@ pub struct MsgPackEntry {
     /// The value itself
     pub data: MsgPackValue,
 }
+
+/// MsgPackEntry as it's deserialized, before `basic_type` is checked against the data
+#[derive(Deserialize)]
+struct UncheckedEntry {
+    raw_marker: u8,
+    basic_type: BasicTypes,
+    data: MsgPackValue,
+}
+impl TryFrom<UncheckedEntry> for MsgPackEntry {
+    type Error = String;
+
+    fn try_from(entry: UncheckedEntry) -> Result<Self, String> {
+        let expected: BasicTypes = value2type(&entry.data);
+        if entry.basic_type != expected {
+            return Err(format!(
+                "basic_type {:?} doesn't match data of type {}, expected {:?}", 
+                entry.basic_type, entry.data.marker_name(), expected
+            ));
+        }
+        Ok(Self { raw_marker: entry.raw_marker, basic_type: entry.basic_type, data: entry.data })
+    }
+}
+impl Default for MsgPackEntry {
+    /// Returns a Null entry with its 0xC0 marker
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// let entry = rmpp::MsgPackEntry::default();
+    /// assert_eq!(vec![0xC0], rmpp::pack(&entry));
+    /// ```
+    fn default() -> Self {
+        Self::from(MsgPackValue::Null)
+    }
+}
 impl MsgPackEntry {
     pub fn new(raw_marker: u8, value: MsgPackValue) -> Self {
         Self { raw_marker, basic_type: value2type(&value), data: value }
     }
+
+    /// Gathers aggregate statistics of the whole tree, recursing through arrays and maps
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// let input = vec![0x82, 0xA1, 0x61, 0x91, 0x01, 0xA1, 0x62, 0xC4, 0x02, 0xDE, 0xAD];
+    /// let stats = rmpp::unpack(&input).unwrap().stats();
+    /// 
+    /// assert_eq!((1, 1, 2, 1, 1), (stats.map, stats.array, stats.string, stats.number, stats.bin));
+    /// assert_eq!((6, 2), (stats.nodes, stats.max_depth));
+    /// assert_eq!((2, 2), (stats.string_bytes, stats.bin_bytes));
+    /// ```
+    pub fn stats(&self) -> MsgPackStats {
+        let mut stats = MsgPackStats::default();
+        stats.visit(self, 0);
+        stats
+    }
+
+    /// Renders an indented tree with the marker name and value of every node, meant for debugging
+    /// 
+    /// Map values are preceded by their keys, binary shows its length and the first bytes in hex
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// let input = vec![0x82, 0xA1, 0x6B, 0x92, 0xCC, 0x03, 0xC0, 0xA1, 0x62, 0xC4, 0x02, 0xDE, 0xAD];
+    /// let tree = rmpp::unpack(&input).unwrap().pretty_tree();
+    /// 
+    /// assert_eq!(tree, concat!(
+    ///     "FixMap(2)\n",
+    ///     "  \"k\": FixArray(2)\n",
+    ///     "    U8(3)\n",
+    ///     "    Null\n",
+    ///     "  \"b\": Bin8(2 bytes: dead)\n",
+    /// ));
+    /// ```
+    pub fn pretty_tree(&self) -> String {
+        let mut tree = String::new();
+        write_tree(&mut tree, &self.data, 0, "");
+        tree
+    }
+
+    /// Tells whether the entry encodes to exactly the expected bytes, without building the encoded buffer
+    /// 
+    /// Encoding stops at the first mismatching byte, an entry that fails to encode matches nothing
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// let entry = rmpp::msgpack!([1, "a"]);
+    /// 
+    /// assert!(entry.encodes_to(&[0x92, 0x01, 0xA1, 0x61]));
+    /// assert!(!entry.encodes_to(&[0x92, 0x01, 0xA1, 0x62]));
+    /// assert!(!entry.encodes_to(&[0x92, 0x01, 0xA1]));
+    /// assert!(!entry.encodes_to(&[0x92, 0x01, 0xA1, 0x61, 0xC0]));
+    /// ```
+    pub fn encodes_to(&self, expected: &[u8]) -> bool {
+        let mut sink = CompareSink { expected };
+        crate::encode::write_value(&mut sink, &self.data).is_ok() && sink.expected.is_empty()
+    }
+
+    /// Computes the CRC-32 (IEEE, as in zlib) checksum of the canonical encoding, see `pack_canonical`
+    /// 
+    /// Logically equal entries get the same checksum no matter their markers or map order
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if a timestamp holds more than 999999999 nanoseconds, like `pack_canonical`
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// assert_eq!(0xD06F7C87, rmpp::msgpack!(true).crc32());
+    /// 
+    /// // {"b": 2, "a": 1} with a U8 marker
+    /// let input = vec![0x82, 0xA1, 0x62, 0xCC, 0x02, 0xA1, 0x61, 0x01];
+    /// assert_eq!(rmpp::msgpack!({"a": 1, "b": 2}).crc32(), rmpp::unpack(&input).unwrap().crc32());
+    /// ```
+    pub fn crc32(&self) -> u32 {
+        crate::encode::crc32(&crate::encode::pack_canonical(self))
+    }
+
+    /// Looks up a nested entry by a JSON Pointer (RFC 6901) path, e.g. `/users/0/name`
+    /// 
+    /// Segments match string keys of maps and indices of arrays, with `~1` standing for `/` and `~0` for `~`. 
+    /// An empty path points at the entry itself. 
+    /// Returns None if the path doesn't start with `/` or leads nowhere
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// let entry = rmpp::msgpack!({"users": [{"name": "ann"}, {"name": "bob"}], "a/b": 1});
+    /// 
+    /// assert_eq!(Some("bob"), entry.pointer("/users/1/name").and_then(|e| e.data.as_str()));
+    /// assert_eq!(Some(1), entry.pointer("/a~1b").and_then(|e| e.data.as_u64()));
+    /// assert_eq!(Some(&entry), entry.pointer(""));
+    /// 
+    /// assert!(entry.pointer("/users/2").is_none());
+    /// assert!(entry.pointer("/users/01").is_none());
+    /// assert!(entry.pointer("users").is_none());
+    /// ```
+    pub fn pointer(&self, path: &str) -> Option<&MsgPackEntry> {
+        pointer_segments(path)?.try_fold(self, |entry, segment| {
+            match &entry.data {
+                MsgPackValue::FixArray(values)|MsgPackValue::Array16(values)|MsgPackValue::Array32(values) => {
+                    values.get(pointer_index(&segment)?)
+                },
+                value => value.get(&segment)
+            }
+        })
+    }
+
+    /// Looks up a nested entry by a JSON Pointer (RFC 6901) path like `pointer`, returning it mutably
+    /// 
+    /// Markers of the collections on the way aren't updated, so use `set` to add elements or keys
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// let mut entry = rmpp::msgpack!({"flags": {"on": false}, "count": 1});
+    /// 
+    /// *entry.pointer_mut("/flags/on").unwrap() = rmpp::msgpack!(true);
+    /// assert_eq!(rmpp::msgpack!({"flags": {"on": true}, "count": 1}), entry);
+    /// ```
+    pub fn pointer_mut(&mut self, path: &str) -> Option<&mut MsgPackEntry> {
+        pointer_segments(path)?.try_fold(self, |entry, segment| {
+            match &mut entry.data {
+                MsgPackValue::FixArray(values)|MsgPackValue::Array16(values)|MsgPackValue::Array32(values) => {
+                    values.get_mut(pointer_index(&segment)?)
+                },
+                MsgPackValue::FixMap(values)|MsgPackValue::Map16(values)|MsgPackValue::Map32(values) => {
+                    values.iter_mut().find(|(k, _)| k.data.as_str() == Some(&segment)).map(|(_, v)| v)
+                },
+                _ => None
+            }
+        })
+    }
+
+    /// Puts a value at a JSON Pointer (RFC 6901) path, replacing whatever was there
+    /// 
+    /// - A key missing from a map is appended to it as a string, an empty path replaces the entry itself
+    /// - An index past the end of an array extends it, filling the gap with Nulls, and `-` stands for the end
+    /// - Missing intermediate values are created as empty maps, so `/a/b` on `{}` gives `{"a": {"b": value}}`
+    /// - A segment can't step into anything but a map or an array, nor into an array by something other than an index, 
+    ///   which is an error rather than replacing the intermediate value
+    /// 
+    /// Collections along the way are widened and get their markers updated as they grow. Nothing is changed on error
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// let mut entry = rmpp::msgpack!({"count": 1, "tags": ["a"]});
+    /// 
+    /// entry.set("/count", rmpp::msgpack!(2)).unwrap();
+    /// entry.set("/tags/-", rmpp::msgpack!("b")).unwrap();
+    /// entry.set("/tags/3", rmpp::msgpack!("d")).unwrap();
+    /// entry.set("/meta/owner", rmpp::msgpack!("ann")).unwrap();
+    /// 
+    /// let expected = rmpp::msgpack!({"count": 2, "tags": ["a", "b", null, "d"], "meta": {"owner": "ann"}});
+    /// assert_eq!(rmpp::pack(&expected), rmpp::pack(&entry));
+    /// assert_eq!(expected, entry);
+    /// 
+    /// assert!(entry.set("/count/x", rmpp::msgpack!(3)).is_err());
+    /// assert!(entry.set("/tags/x", rmpp::msgpack!(3)).is_err());
+    /// assert!(entry.set("count", rmpp::msgpack!(3)).is_err());
+    /// ```
+    pub fn set(&mut self, path: &str, value: MsgPackEntry) -> Result<(), MsgPackError> {
+        let segments: Vec<String> = pointer_segments(path)
+            .ok_or_else(|| MsgPackError::Custom(format!("Pointer {:?} doesn't start with '/'", path)))?
+            .collect();
+        pointer_set(self, &segments, value)
+    }
+
+    /// Lists every scalar of the tree along with its JSON Pointer (RFC 6901) path, in wire order
+    /// 
+    /// Array elements go by their indices and map values by their string keys, with `/` and `~` escaped as `~1` and `~0`. 
+    /// Any other map key is written as `~[key]` with the key's display form, e.g. `/~[1]` for the integer key 1, 
+    ///     which never collides with an escaped string key. 
+    /// Empty arrays and maps are listed too, so that they don't go unnoticed when comparing trees
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// use rmpp::MsgPackValue;
+    /// 
+    /// let entry = rmpp::msgpack!({"a": {"b": [true, null]}, "c/d": [], "e": 1});
+    /// let flat = entry.flatten();
+    /// 
+    /// let paths: Vec<&str> = flat.iter().map(|(path, _)| path.as_str()).collect();
+    /// assert_eq!(vec!["/a/b/0", "/a/b/1", "/c~1d", "/e"], paths);
+    /// assert_eq!(MsgPackValue::Bool(true), flat[0].1);
+    /// 
+    /// for (path, value) in &flat {
+    ///     assert_eq!(value, &entry.pointer(path).unwrap().data);
+    /// }
+    /// 
+    /// // Non-string keys
+    /// let input = vec![0x81, 0x01, 0xC3];
+    /// assert_eq!(vec![("/~[1]".to_string(), MsgPackValue::Bool(true))], rmpp::unpack(&input).unwrap().flatten());
+    /// ```
+    pub fn flatten(&self) -> Vec<(String, MsgPackValue)> {
+        let mut res: Vec<(String, MsgPackValue)> = vec![];
+        flatten_into(&self.data, &mut String::new(), &mut res);
+        res
+    }
+
+    /// Lists the scalars that were added, removed or changed between this tree and another one, see `flatten`
+    /// 
+    /// Scalars are compared structurally, so a value re-encoded with another marker shows up as changed, 
+    ///     see `diff_with` to ignore marker widths. 
+    /// Removed and changed paths come in the order of this tree, followed by the added ones in the order of the other. 
+    /// A scalar replaced by a collection, or the other way around, is removed from one path and added at others
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// use rmpp::{DiffEntry, MsgPackValue};
+    /// 
+    /// let old = rmpp::msgpack!({"name": "a", "tags": ["x", "y"], "on": true});
+    /// let new = rmpp::msgpack!({"name": "b", "tags": ["x"], "on": true, "n": 1});
+    /// 
+    /// assert_eq!(vec![
+    ///     DiffEntry::Changed { 
+    ///         path: "/name".to_string(), 
+    ///         old: MsgPackValue::FixStr("a".to_string()), new: MsgPackValue::FixStr("b".to_string()),
+    ///     },
+    ///     DiffEntry::Removed { path: "/tags/1".to_string(), value: MsgPackValue::FixStr("y".to_string()) },
+    ///     DiffEntry::Added { path: "/n".to_string(), value: MsgPackValue::FixPos(1) },
+    /// ], old.diff(&new));
+    /// 
+    /// assert!(old.diff(&old).is_empty());
+    /// ```
+    pub fn diff(&self, other: &MsgPackEntry) -> Vec<DiffEntry> {
+        self.diff_with(other, false)
+    }
+
+    /// Lists the scalars that differ between this tree and another one like `diff`, 
+    ///     optionally comparing them with `MsgPackValue::logically_eq` so that marker widths are ignored
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// // {"n": 1} with a U16 marker
+    /// let wide = rmpp::unpack(&[0x81, 0xA1, 0x6E, 0xCD, 0x00, 0x01]).unwrap();
+    /// let narrow = rmpp::msgpack!({"n": 1});
+    /// 
+    /// assert_eq!(1, wide.diff_with(&narrow, false).len());
+    /// assert!(wide.diff_with(&narrow, true).is_empty());
+    /// ```
+    pub fn diff_with(&self, other: &MsgPackEntry, logical: bool) -> Vec<DiffEntry> {
+        let (old, new) = (self.flatten(), other.flatten());
+        let old_paths: BTreeMap<&str, &MsgPackValue> = old.iter().map(|(path, v)| (path.as_str(), v)).collect();
+        let new_paths: BTreeMap<&str, &MsgPackValue> = new.iter().map(|(path, v)| (path.as_str(), v)).collect();
+        let differ = |a: &MsgPackValue, b: &MsgPackValue| if logical { !a.logically_eq(b) } else { a != b };
+        let mut res: Vec<DiffEntry> = vec![];
+
+        for (path, value) in &old {
+            match new_paths.get(path.as_str()) {
+                None => res.push(DiffEntry::Removed { path: path.clone(), value: value.clone() }),
+                Some(new) if differ(value, new) => {
+                    res.push(DiffEntry::Changed { path: path.clone(), old: value.clone(), new: (*new).clone() });
+                },
+                Some(_) => {}
+            }
+        }
+        for (path, value) in new {
+            if !old_paths.contains_key(path.as_str()) { res.push(DiffEntry::Added { path, value }); }
+        }
+
+        res
+    }
+}
+
+/// Writer comparing everything written against the expected bytes, failing on the first mismatch
+struct CompareSink<'a> {
+    /// Expected bytes not written yet
+    expected: &'a [u8],
+}
+impl io::Write for CompareSink<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.expected.strip_prefix(buf) {
+            Some(rest) => { self.expected = rest; Ok(buf.len()) },
+            None => Err(io::Error::from(io::ErrorKind::InvalidData))
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Difference at a single path between two MsgPackEntry trees, see `MsgPackEntry::diff`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffEntry {
+    /// Only the other tree has a value at the path
+    Added { path: String, value: MsgPackValue },
+    /// Only this tree has a value at the path
+    Removed { path: String, value: MsgPackValue },
+    /// Both trees have a value at the path and they differ
+    Changed { path: String, old: MsgPackValue, new: MsgPackValue },
+}
+
+/// Aggregate statistics of a MsgPackEntry tree, see `MsgPackEntry::stats`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MsgPackStats {
+    /// Number of nodes of each basic type
+    pub null: usize,
+    pub bool: usize,
+    pub number: usize,
+    pub string: usize,
+    pub bin: usize,
+    pub array: usize,
+    pub map: usize,
+    pub ext: usize,
+    /// Total number of nodes, map keys included
+    pub nodes: usize,
+    /// Deepest nesting of arrays and maps, a scalar has the depth of 0
+    pub max_depth: usize,
+    /// Total length of all strings in bytes
+    pub string_bytes: usize,
+    /// Total length of all binary values in bytes
+    pub bin_bytes: usize,
+}
+impl MsgPackStats {
+    /// Accounts for an entry and its children found at the given depth
+    fn visit(&mut self, entry: &MsgPackEntry, depth: usize) {
+        self.nodes += 1;
+        self.max_depth = self.max_depth.max(depth);
+
+        match value2type(&entry.data) {
+            BasicTypes::Null => self.null += 1,
+            BasicTypes::Bool => self.bool += 1,
+            BasicTypes::Number => self.number += 1,
+            BasicTypes::String => {
+                self.string += 1;
+                self.string_bytes += entry.data.len().unwrap_or(0);
+            },
+            BasicTypes::Bin => {
+                self.bin += 1;
+                self.bin_bytes += entry.data.len().unwrap_or(0);
+            },
+            BasicTypes::Array => {
+                self.array += 1;
+                for value in entry.data.as_array().unwrap_or(&[]) { self.visit(value, depth + 1); }
+            },
+            BasicTypes::Map => {
+                self.map += 1;
+                for (k, v) in entry.data.as_map().unwrap_or(&[]) {
+                    self.visit(k, depth + 1);
+                    self.visit(v, depth + 1);
+                }
+            },
+            BasicTypes::Ext => self.ext += 1,
+        }
+    }
+}
+impl core::fmt::Display for MsgPackEntry {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.data)
+    }
+}
+impl From<MsgPackValue> for MsgPackEntry {
+    /// Wraps a value, deriving the raw marker it gets encoded with
+    fn from(value: MsgPackValue) -> Self {
+        Self::new(value2marker(&value), value)
+    }
+}
+impl PartialOrd for MsgPackEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for MsgPackEntry {
+    /// Orders entries by their values, see `MsgPackValue`'s ordering
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.data.cmp(&other.data)
+            .then_with(|| self.raw_marker.cmp(&other.raw_marker))
+            .then_with(|| self.basic_type.cmp(&other.basic_type))
+    }
 }
 impl MsgValue for MsgPackEntry {
     fn get_value(&self) -> &MsgPackValue {
@@ -29,32 +464,767 @@ impl MsgValue for MsgPackEntry {
 }
 
 /// Holds an actual type and value
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// 
+/// Equality and hashing are structural: values are equal when they're of the same variant holding the same data, 
+///     so values that are logically equal but have different markers hash differently, see `logically_eq` for that. 
+/// Floats are compared by their bits, which makes NaN equal to itself and tells 0.0 apart from -0.0
+/// 
+/// # Examples 
+/// 
+/// ```
+/// use std::collections::HashSet;
+/// use rmpp::MsgPackValue;
+/// 
+/// let set: HashSet<MsgPackValue> = [
+///     MsgPackValue::U8(1), MsgPackValue::U8(1), MsgPackValue::U16(1), 
+///     MsgPackValue::F64(f64::NAN), MsgPackValue::F64(f64::NAN), MsgPackValue::F64(-0.0),
+/// ].into_iter().collect();
+/// 
+/// assert_eq!(4, set.len());
+/// assert_ne!(MsgPackValue::F64(0.0), MsgPackValue::F64(-0.0));
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[serde(tag = "type", content = "value")]
 pub enum MsgPackValue {
+    #[default]
     Null,
     Bool(bool), // It doesn't make sense to separate it into False and True
     FixPos(u8), FixNeg(i8),
     U8(u8), U16(u16), U32(u32), U64(u64),
     I8(i8), I16(i16), I32(i32), I64(i64),
-    F32(f32), F64(f64),
+    F32(#[serde(with = "crate::json::float32")] f32),
+    F64(#[serde(with = "crate::json::float64")] f64), // NaN and infinity are represented as "NaN", "Infinity" and "-Infinity" in JSON
     FixStr(String), Str8(String), Str16(String), Str32(String),
-    Bin8(Vec<u8>), Bin16(Vec<u8>), Bin32(Vec<u8>),
+    #[serde(with = "crate::json::base64")]
+    StrRaw(Vec<u8>), // String that isn't valid UTF-8, encoded with the narrowest string marker
+    Bin8(#[serde(deserialize_with = "crate::json::bytes::deserialize")] Vec<u8>), // Binary is read from either an array of bytes or a hex string
+    Bin16(#[serde(deserialize_with = "crate::json::bytes::deserialize")] Vec<u8>),
+    Bin32(#[serde(deserialize_with = "crate::json::bytes::deserialize")] Vec<u8>),
     FixArray(Vec<MsgPackEntry>), Array16(Vec<MsgPackEntry>), Array32(Vec<MsgPackEntry>),
     FixMap(Vec<(MsgPackEntry, MsgPackEntry)>), Map16(Vec<(MsgPackEntry, MsgPackEntry)>), Map32(Vec<(MsgPackEntry, MsgPackEntry)>),
-    // Ext(i8, Vec<u8>),
+    Ext(i8, Vec<u8>), // Extension type and its payload
+    Timestamp { seconds: i64, nanos: u32 }, // Reserved extension type -1
+}
+impl PartialEq for MsgPackValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (MsgPackValue::Null, MsgPackValue::Null) => true,
+            (MsgPackValue::Bool(a), MsgPackValue::Bool(b)) => a == b,
+            (MsgPackValue::FixPos(a), MsgPackValue::FixPos(b)) => a == b,
+            (MsgPackValue::FixNeg(a), MsgPackValue::FixNeg(b)) => a == b,
+            (MsgPackValue::U8(a), MsgPackValue::U8(b)) => a == b,
+            (MsgPackValue::U16(a), MsgPackValue::U16(b)) => a == b,
+            (MsgPackValue::U32(a), MsgPackValue::U32(b)) => a == b,
+            (MsgPackValue::U64(a), MsgPackValue::U64(b)) => a == b,
+            (MsgPackValue::I8(a), MsgPackValue::I8(b)) => a == b,
+            (MsgPackValue::I16(a), MsgPackValue::I16(b)) => a == b,
+            (MsgPackValue::I32(a), MsgPackValue::I32(b)) => a == b,
+            (MsgPackValue::I64(a), MsgPackValue::I64(b)) => a == b,
+            // Floats are compared by their bits
+            (MsgPackValue::F32(a), MsgPackValue::F32(b)) => a.to_bits() == b.to_bits(),
+            (MsgPackValue::F64(a), MsgPackValue::F64(b)) => a.to_bits() == b.to_bits(),
+            (MsgPackValue::FixStr(a), MsgPackValue::FixStr(b)) => a == b,
+            (MsgPackValue::Str8(a), MsgPackValue::Str8(b)) => a == b,
+            (MsgPackValue::Str16(a), MsgPackValue::Str16(b)) => a == b,
+            (MsgPackValue::Str32(a), MsgPackValue::Str32(b)) => a == b,
+            (MsgPackValue::StrRaw(a), MsgPackValue::StrRaw(b)) => a == b,
+            (MsgPackValue::Bin8(a), MsgPackValue::Bin8(b)) => a == b,
+            (MsgPackValue::Bin16(a), MsgPackValue::Bin16(b)) => a == b,
+            (MsgPackValue::Bin32(a), MsgPackValue::Bin32(b)) => a == b,
+            (MsgPackValue::FixArray(a), MsgPackValue::FixArray(b)) => a == b,
+            (MsgPackValue::Array16(a), MsgPackValue::Array16(b)) => a == b,
+            (MsgPackValue::Array32(a), MsgPackValue::Array32(b)) => a == b,
+            (MsgPackValue::FixMap(a), MsgPackValue::FixMap(b)) => a == b,
+            (MsgPackValue::Map16(a), MsgPackValue::Map16(b)) => a == b,
+            (MsgPackValue::Map32(a), MsgPackValue::Map32(b)) => a == b,
+            (MsgPackValue::Ext(at, a), MsgPackValue::Ext(bt, b)) => at == bt && a == b,
+            (MsgPackValue::Timestamp { seconds: a, nanos: an }, MsgPackValue::Timestamp { seconds: b, nanos: bn }) => {
+                a == b && an == bn
+            },
+            _ => false
+        }
+    }
+}
+impl Eq for MsgPackValue {}
+impl Hash for MsgPackValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
+        match self {
+            MsgPackValue::Null => {},
+            MsgPackValue::Bool(b) => b.hash(state),
+            MsgPackValue::FixPos(n)|MsgPackValue::U8(n) => n.hash(state),
+            MsgPackValue::FixNeg(n)|MsgPackValue::I8(n) => n.hash(state),
+            MsgPackValue::U16(n) => n.hash(state),
+            MsgPackValue::U32(n) => n.hash(state),
+            MsgPackValue::U64(n) => n.hash(state),
+            MsgPackValue::I16(n) => n.hash(state),
+            MsgPackValue::I32(n) => n.hash(state),
+            MsgPackValue::I64(n) => n.hash(state),
+            // Floats are hashed by their bits
+            MsgPackValue::F32(n) => n.to_bits().hash(state),
+            MsgPackValue::F64(n) => n.to_bits().hash(state),
+            MsgPackValue::FixStr(s)|MsgPackValue::Str8(s)|
+            MsgPackValue::Str16(s)|MsgPackValue::Str32(s) => s.hash(state),
+            MsgPackValue::StrRaw(b)|MsgPackValue::Bin8(b)|MsgPackValue::Bin16(b)|MsgPackValue::Bin32(b) => b.hash(state),
+            MsgPackValue::FixArray(values)|MsgPackValue::Array16(values)|MsgPackValue::Array32(values) => values.hash(state),
+            MsgPackValue::FixMap(values)|MsgPackValue::Map16(values)|MsgPackValue::Map32(values) => values.hash(state),
+            MsgPackValue::Ext(t, b) => { t.hash(state); b.hash(state); },
+            MsgPackValue::Timestamp { seconds, nanos } => { seconds.hash(state); nanos.hash(state); }
+        }
+    }
+}
+
+impl PartialOrd for MsgPackValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for MsgPackValue {
+    /// Orders values by their basic type, then by their logical value, then by their marker
+    /// 
+    /// - Basic types go in the order of Null, Bool, Number, String, Bin, Array, Map, Ext
+    /// - Booleans go false first, numbers by their exact value whether they're integers or floats, 
+    ///   floats by `f64::total_cmp` (-0.0 comes right before zero, NaN goes to the end matching its sign)
+    /// - Strings and binary go by their bytes, arrays and maps element by element
+    /// - Extensions go by their type, then by their data, a timestamp being of type -1
+    /// - Values of equal logical value go by their marker byte, StrRaw coming after a string of the same marker
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// use rmpp::MsgPackValue;
+    /// 
+    /// let mut values = vec![
+    ///     MsgPackValue::FixStr("a".to_string()), MsgPackValue::F64(1.5), MsgPackValue::U16(1), 
+    ///     MsgPackValue::Null, MsgPackValue::FixPos(1), MsgPackValue::I8(-3), MsgPackValue::F64(f64::NAN),
+    /// ];
+    /// values.sort();
+    /// 
+    /// assert_eq!(vec![
+    ///     MsgPackValue::Null, MsgPackValue::I8(-3), MsgPackValue::FixPos(1), MsgPackValue::U16(1), 
+    ///     MsgPackValue::F64(1.5), MsgPackValue::F64(f64::NAN), MsgPackValue::FixStr("a".to_string()),
+    /// ], values);
+    /// ```
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.logical_cmp(other)
+            .then_with(|| value2marker(self).cmp(&value2marker(other)))
+            .then_with(|| matches!(self, MsgPackValue::StrRaw(_)).cmp(&matches!(other, MsgPackValue::StrRaw(_))))
+            // Logically equal collections of the same marker can still hold elements of different markers
+            .then_with(|| match (self.as_array(), other.as_array()) {
+                (Some(a), Some(b)) => a.cmp(b),
+                _ => self.as_map().cmp(&other.as_map())
+            })
+    }
+}
+
+impl MsgPackValue {
+    /// Returns the value as i64 if it's an integer that fits
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// assert_eq!(Some(1), rmpp::MsgPackValue::U64(1).as_i64());
+    /// assert_eq!(Some(-1), rmpp::MsgPackValue::FixNeg(-1).as_i64());
+    /// assert_eq!(None, rmpp::MsgPackValue::U64(u64::MAX).as_i64());
+    /// ```
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            MsgPackValue::FixPos(n) => Some(i64::from(*n)),
+            MsgPackValue::FixNeg(n) => Some(i64::from(*n)),
+            MsgPackValue::U8(n)  => Some(i64::from(*n)),
+            MsgPackValue::U16(n) => Some(i64::from(*n)),
+            MsgPackValue::U32(n) => Some(i64::from(*n)),
+            MsgPackValue::U64(n) => i64::try_from(*n).ok(),
+            MsgPackValue::I8(n)  => Some(i64::from(*n)),
+            MsgPackValue::I16(n) => Some(i64::from(*n)),
+            MsgPackValue::I32(n) => Some(i64::from(*n)),
+            MsgPackValue::I64(n) => Some(*n),
+            _ => None
+        }
+    }
+
+    /// Returns the value as u64 if it's an integer that fits
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            MsgPackValue::U64(n) => Some(*n),
+            _ => self.as_i64().and_then(|n| u64::try_from(n).ok())
+        }
+    }
+
+    /// Returns the value as f64 if it's a number
+    /// 
+    /// Integers too large for f64 lose precision
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            MsgPackValue::F32(n) => Some(f64::from(*n)),
+            MsgPackValue::F64(n) => Some(*n),
+            MsgPackValue::U64(n) => Some(*n as f64),
+            _ => self.as_i64().map(|n| n as f64)
+        }
+    }
+
+    /// Converts an integer of any marker into the given integer type, failing if it doesn't fit
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// use rmpp::MsgPackValue;
+    /// 
+    /// assert_eq!(300u16, MsgPackValue::U32(300).try_as::<u16>().unwrap());
+    /// assert_eq!(-1i32, MsgPackValue::FixNeg(-1).try_as::<i32>().unwrap());
+    /// assert_eq!("300 doesn't fit u8", MsgPackValue::U16(300).try_as::<u8>().unwrap_err().to_string());
+    /// assert_eq!("Expected an integer, got F64", MsgPackValue::F64(1.0).try_as::<u8>().unwrap_err().to_string());
+    /// ```
+    pub fn try_as<T: TryFrom<i128>>(&self) -> Result<T, MsgPackError> {
+        let n: i128 = self.as_int()
+            .ok_or_else(|| MsgPackError::Custom(format!("Expected an integer, got {}", self.marker_name())))?;
+        T::try_from(n).map_err(|_| MsgPackError::Custom(format!("{} doesn't fit {}", n, core::any::type_name::<T>())))
+    }
+
+    /// Returns the value as a string slice if it's a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MsgPackValue::FixStr(s)|MsgPackValue::Str8(s)|
+            MsgPackValue::Str16(s)|MsgPackValue::Str32(s) => Some(s),
+            _ => None
+        }
+    }
+
+    /// Returns the value as a byte slice if it's binary
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            MsgPackValue::Bin8(b)|MsgPackValue::Bin16(b)|MsgPackValue::Bin32(b) => Some(b),
+            _ => None
+        }
+    }
+
+    /// Returns binary as a string slice if its bytes happen to be valid UTF-8
+    /// 
+    /// Helps to spot text that an encoder mislabeled as binary
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// use rmpp::MsgPackValue;
+    /// 
+    /// assert_eq!(Some("hi"), MsgPackValue::Bin8(b"hi".to_vec()).bin_as_utf8());
+    /// assert_eq!(None, MsgPackValue::Bin8(vec![0xFF]).bin_as_utf8());
+    /// assert_eq!(None, MsgPackValue::FixStr("hi".to_string()).bin_as_utf8());
+    /// ```
+    pub fn bin_as_utf8(&self) -> Option<&str> {
+        core::str::from_utf8(self.as_bytes()?).ok()
+    }
+
+    /// Returns a string or binary ending with a NUL byte as a string slice without the terminator
+    /// 
+    /// Returns None if there's no trailing NUL or the rest isn't valid UTF-8
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// use rmpp::MsgPackValue;
+    /// 
+    /// assert_eq!(Some("hi"), MsgPackValue::Bin8(b"hi\0".to_vec()).as_cstr());
+    /// assert_eq!(Some("hi"), MsgPackValue::FixStr("hi\0".to_string()).as_cstr());
+    /// assert_eq!(None, MsgPackValue::Bin8(b"hi".to_vec()).as_cstr());
+    /// assert_eq!(None, MsgPackValue::Bin8(vec![0xFF, 0x00]).as_cstr());
+    /// ```
+    pub fn as_cstr(&self) -> Option<&str> {
+        let bytes: &[u8] = self.as_bytes().or_else(|| self.str_bytes())?;
+        core::str::from_utf8(bytes.strip_suffix(&[0])?).ok()
+    }
+
+    /// Returns the elements if the value is an array
+    pub fn as_array(&self) -> Option<&[MsgPackEntry]> {
+        match self {
+            MsgPackValue::FixArray(values)|MsgPackValue::Array16(values)|MsgPackValue::Array32(values) => Some(values),
+            _ => None
+        }
+    }
+
+    /// Returns the key-value pairs if the value is a map
+    pub fn as_map(&self) -> Option<&[(MsgPackEntry, MsgPackEntry)]> {
+        match self {
+            MsgPackValue::FixMap(values)|MsgPackValue::Map16(values)|MsgPackValue::Map32(values) => Some(values),
+            _ => None
+        }
+    }
+
+    /// Looks up a map value by its string key, returning the first match
+    /// 
+    /// Returns None if the value isn't a map or has no such key
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// let input = vec![0x81, 0xA1, 0x6B, 0x01];
+    /// let value = rmpp::unpack(&input).unwrap();
+    /// 
+    /// assert_eq!(rmpp::MsgPackValue::FixPos(1), value.data.get("k").unwrap().data);
+    /// assert!(value.data.get("x").is_none());
+    /// ```
+    pub fn get(&self, key: &str) -> Option<&MsgPackEntry> {
+        self.as_map()?.iter().find(|(k, _)| k.data.as_str() == Some(key)).map(|(_, v)| v)
+    }
+
+    /// Overlays another map onto this one, recursing into the maps found under the same keys on both sides
+    /// 
+    /// Keys are matched with `logically_eq`, so the same string under different markers is the same key. 
+    /// Keys missing from this map are appended in the order of `other`, any other value under a matching key is replaced. 
+    /// If either side isn't a map, this value is replaced with `other` wholesale, the same way a nested value would be. 
+    /// A FixMap or Map16 that outgrows its length field is widened, 
+    ///     the `raw_marker` of the entry holding this value is left for the caller to update
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// // {"a": 1, "nested": {"x": true, "y": false}}
+    /// let base = vec![0x82, 0xA1, 0x61, 0x01, 0xA6, 0x6E, 0x65, 0x73, 0x74, 0x65, 0x64, 0x82, 0xA1, 0x78, 0xC3, 0xA1, 0x79, 0xC2];
+    /// // {"nested": {"y": true, "z": null}, "b": 2}, with a Str8 key
+    /// let patch = vec![0x82, 0xD9, 0x06, 0x6E, 0x65, 0x73, 0x74, 0x65, 0x64, 0x82, 0xA1, 0x79, 0xC3, 0xA1, 0x7A, 0xC0, 0xA1, 0x62, 0x02];
+    /// 
+    /// let mut value = rmpp::unpack(&base).unwrap().data;
+    /// value.merge(&rmpp::unpack(&patch).unwrap().data);
+    /// 
+    /// // {"a": 1, "nested": {"x": true, "y": true, "z": null}, "b": 2}
+    /// let merged = rmpp::unpack(&[
+    ///     0x83, 0xA1, 0x61, 0x01, 0xA6, 0x6E, 0x65, 0x73, 0x74, 0x65, 0x64, 
+    ///     0x83, 0xA1, 0x78, 0xC3, 0xA1, 0x79, 0xC3, 0xA1, 0x7A, 0xC0, 0xA1, 0x62, 0x02,
+    /// ]).unwrap();
+    /// assert_eq!(merged.data, value);
+    /// 
+    /// // Anything but a map is replaced
+    /// let mut value = rmpp::MsgPackValue::U8(1);
+    /// value.merge(&merged.data);
+    /// assert_eq!(merged.data, value);
+    /// ```
+    pub fn merge(&mut self, other: &MsgPackValue) {
+        let Some(patch) = other.as_map() else { *self = other.clone(); return; };
+        let (MsgPackValue::FixMap(values)|MsgPackValue::Map16(values)|MsgPackValue::Map32(values)) = self else {
+            *self = other.clone();
+            return;
+        };
+
+        for (k, v) in patch {
+            match values.iter_mut().find(|(key, _)| key.data.logically_eq(&k.data)) {
+                Some((_, value)) if value.data.as_map().is_some() && v.data.as_map().is_some() => {
+                    value.data.merge(&v.data);
+                    value.raw_marker = value2marker(&value.data);
+                },
+                Some((_, value)) => *value = v.clone(),
+                None => values.push((k.clone(), v.clone()))
+            }
+        }
+
+        fit_length(self);
+    }
+
+    /// Returns the number of elements of an array, pairs of a map or bytes of a string or binary
+    /// 
+    /// Returns None for any other value
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// let input = vec![0x92, 0xA2, 0x68, 0x69, 0x80];
+    /// let value = rmpp::unpack(&input).unwrap();
+    /// 
+    /// assert_eq!(Some(2), value.data.len());
+    /// assert_eq!(Some(2), value.data.as_array().unwrap()[0].data.len());
+    /// assert_eq!(Some(true), value.data.as_array().unwrap()[1].data.is_empty());
+    /// assert_eq!(None, rmpp::MsgPackValue::U8(1).len());
+    /// ```
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            MsgPackValue::FixArray(values)|MsgPackValue::Array16(values)|MsgPackValue::Array32(values) => Some(values.len()),
+            MsgPackValue::FixMap(values)|MsgPackValue::Map16(values)|MsgPackValue::Map32(values) => Some(values.len()),
+            MsgPackValue::Bin8(b)|MsgPackValue::Bin16(b)|MsgPackValue::Bin32(b) => Some(b.len()),
+            _ => self.str_bytes().map(<[u8]>::len)
+        }
+    }
+
+    /// Returns whether an array, a map, a string or binary is empty
+    /// 
+    /// Returns None for any other value
+    pub fn is_empty(&self) -> Option<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Returns whether the value is an array or a map
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// use rmpp::MsgPackValue;
+    /// 
+    /// assert!(MsgPackValue::FixArray(vec![]).is_container());
+    /// assert!(MsgPackValue::Map32(vec![]).is_container());
+    /// assert!(!MsgPackValue::Str8("a".to_string()).is_container());
+    /// assert!(MsgPackValue::Ext(1, vec![0]).is_scalar());
+    /// ```
+    pub fn is_container(&self) -> bool {
+        self.as_array().is_some() || self.as_map().is_some()
+    }
+
+    /// Returns whether the value is anything but an array or a map, strings, binary and extensions included
+    pub fn is_scalar(&self) -> bool {
+        !self.is_container()
+    }
+
+    /// Returns the name of the marker the value is encoded with, matching the variant
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// use rmpp::MsgPackValue;
+    /// 
+    /// assert_eq!("FixPos", MsgPackValue::FixPos(1).marker_name());
+    /// assert_eq!("Str8", MsgPackValue::Str8("a".to_string()).marker_name());
+    /// ```
+    pub fn marker_name(&self) -> &'static str {
+        match self {
+            MsgPackValue::Null => "Null",
+            MsgPackValue::Bool(_) => "Bool",
+            MsgPackValue::FixPos(_) => "FixPos",
+            MsgPackValue::FixNeg(_) => "FixNeg",
+            MsgPackValue::U8(_)  => "U8",
+            MsgPackValue::U16(_) => "U16",
+            MsgPackValue::U32(_) => "U32",
+            MsgPackValue::U64(_) => "U64",
+            MsgPackValue::I8(_)  => "I8",
+            MsgPackValue::I16(_) => "I16",
+            MsgPackValue::I32(_) => "I32",
+            MsgPackValue::I64(_) => "I64",
+            MsgPackValue::F32(_) => "F32",
+            MsgPackValue::F64(_) => "F64",
+            MsgPackValue::FixStr(_) => "FixStr",
+            MsgPackValue::Str8(_)   => "Str8",
+            MsgPackValue::Str16(_)  => "Str16",
+            MsgPackValue::Str32(_)  => "Str32",
+            MsgPackValue::StrRaw(_) => "StrRaw",
+            MsgPackValue::Bin8(_)  => "Bin8",
+            MsgPackValue::Bin16(_) => "Bin16",
+            MsgPackValue::Bin32(_) => "Bin32",
+            MsgPackValue::FixArray(_) => "FixArray",
+            MsgPackValue::Array16(_)  => "Array16",
+            MsgPackValue::Array32(_)  => "Array32",
+            MsgPackValue::FixMap(_) => "FixMap",
+            MsgPackValue::Map16(_)  => "Map16",
+            MsgPackValue::Map32(_)  => "Map32",
+            MsgPackValue::Ext(_,_) => "Ext",
+            MsgPackValue::Timestamp { .. } => "Timestamp",
+        }
+    }
+
+    /// Returns the range of marker bytes the variant encodes to
+    /// 
+    /// Fixed-size variants share a range of bytes that also holds their value or length, 
+    ///     any other variant yields the single marker byte this very value encodes to
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// use rmpp::MsgPackValue;
+    /// 
+    /// assert_eq!(0x00..=0x7F, MsgPackValue::FixPos(1).marker_byte_range());
+    /// assert_eq!(0xE0..=0xFF, MsgPackValue::FixNeg(-1).marker_byte_range());
+    /// assert_eq!(0xCD..=0xCD, MsgPackValue::U16(300).marker_byte_range());
+    /// assert!(MsgPackValue::FixStr("a".to_string()).marker_byte_range().contains(&0xA1));
+    /// ```
+    pub fn marker_byte_range(&self) -> RangeInclusive<u8> {
+        match value2marker(self) {
+            markers::FIXPOS..=0x7F => markers::FIXPOS..=0x7F,
+            markers::FIXMAP..=0x8F => markers::FIXMAP..=0x8F,
+            markers::FIXARRAY..=0x9F => markers::FIXARRAY..=0x9F,
+            markers::FIXSTR..=0xBF => markers::FIXSTR..=0xBF,
+            markers::FIXNEG..=0xFF => markers::FIXNEG..=0xFF,
+            marker => marker..=marker
+        }
+    }
+
+    /// Compares values by their logical content, ignoring the marker width
+    /// 
+    /// Integers compare across all of the integer markers, floats across F32 and F64, 
+    ///     strings, binary, arrays and maps across their length variants. 
+    /// Arrays and maps are compared element by element in order
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// use rmpp::MsgPackValue;
+    /// 
+    /// assert!(MsgPackValue::U8(1).logically_eq(&MsgPackValue::FixPos(1)));
+    /// assert!(MsgPackValue::Str8("a".to_string()).logically_eq(&MsgPackValue::FixStr("a".to_string())));
+    /// assert!(!MsgPackValue::U8(1).logically_eq(&MsgPackValue::F32(1.0)));
+    /// ```
+    pub fn logically_eq(&self, other: &MsgPackValue) -> bool {
+        match (value2type(self), value2type(other)) {
+            (BasicTypes::Number, BasicTypes::Number) => {
+                match (self.as_int(), other.as_int()) {
+                    (Some(a), Some(b)) => a == b,
+                    (None, None) => self.as_f64() == other.as_f64(), // Both are floats
+                    _ => false
+                }
+            },
+            (BasicTypes::String, BasicTypes::String) => self.str_bytes() == other.str_bytes(),
+            (BasicTypes::Bin, BasicTypes::Bin) => self.as_bytes() == other.as_bytes(),
+            (BasicTypes::Array, BasicTypes::Array) => {
+                let (a, b) = (self.as_array().unwrap(), other.as_array().unwrap());
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.data.logically_eq(&b.data))
+            },
+            (BasicTypes::Map, BasicTypes::Map) => {
+                let (a, b) = (self.as_map().unwrap(), other.as_map().unwrap());
+                a.len() == b.len() && a.iter().zip(b).all(|((ak, av), (bk, bv))| {
+                    ak.data.logically_eq(&bk.data) && av.data.logically_eq(&bv.data)
+                })
+            },
+            _ => self == other
+        }
+    }
+
+    /// Orders values by their basic type, then by their logical value, ignoring the markers
+    fn logical_cmp(&self, other: &MsgPackValue) -> Ordering {
+        let (a_type, b_type) = (value2type(self), value2type(other));
+        if a_type != b_type { return a_type.cmp(&b_type); }
+
+        match a_type {
+            BasicTypes::Bool => (self == &MsgPackValue::Bool(true)).cmp(&(other == &MsgPackValue::Bool(true))),
+            BasicTypes::Number => {
+                match (self.as_int(), other.as_int()) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (Some(a), None) => cmp_int_float(a, other.as_f64().unwrap()),
+                    (None, Some(b)) => cmp_int_float(b, self.as_f64().unwrap()).reverse(),
+                    (None, None) => self.as_f64().unwrap().total_cmp(&other.as_f64().unwrap())
+                }
+            },
+            BasicTypes::String => self.str_bytes().cmp(&other.str_bytes()),
+            BasicTypes::Bin => self.as_bytes().cmp(&other.as_bytes()),
+            BasicTypes::Array => {
+                let (a, b) = (self.as_array().unwrap(), other.as_array().unwrap());
+                a.iter().zip(b)
+                    .map(|(a, b)| a.data.logical_cmp(&b.data))
+                    .find(|o| o.is_ne())
+                    .unwrap_or(a.len().cmp(&b.len()))
+            },
+            BasicTypes::Map => {
+                let (a, b) = (self.as_map().unwrap(), other.as_map().unwrap());
+                a.iter().zip(b)
+                    .map(|((ak, av), (bk, bv))| ak.data.logical_cmp(&bk.data).then_with(|| av.data.logical_cmp(&bv.data)))
+                    .find(|o| o.is_ne())
+                    .unwrap_or(a.len().cmp(&b.len()))
+            },
+            BasicTypes::Ext => {
+                match (self, other) {
+                    (MsgPackValue::Timestamp { seconds: a, nanos: an }, MsgPackValue::Timestamp { seconds: b, nanos: bn }) => {
+                        (a, an).cmp(&(b, bn))
+                    },
+                    // A timestamp comes before a raw extension of the same type
+                    (MsgPackValue::Timestamp { .. }, MsgPackValue::Ext(t, _)) => (-1).cmp(t).then(Ordering::Less),
+                    (MsgPackValue::Ext(t, _), MsgPackValue::Timestamp { .. }) => t.cmp(&-1).then(Ordering::Greater),
+                    (MsgPackValue::Ext(at, a), MsgPackValue::Ext(bt, b)) => (at, a).cmp(&(bt, b)),
+                    _ => unreachable!()
+                }
+            },
+            BasicTypes::Null => Ordering::Equal
+        }
+    }
+
+    /// Returns the value as i128 if it's an integer, which holds any of the integer markers
+    pub(crate) fn as_int(&self) -> Option<i128> {
+        match self {
+            MsgPackValue::U64(n) => Some(i128::from(*n)),
+            _ => self.as_i64().map(i128::from)
+        }
+    }
+
+    /// Returns the bytes of a string, whether it's valid UTF-8 or not
+    pub(crate) fn str_bytes(&self) -> Option<&[u8]> {
+        match self {
+            MsgPackValue::StrRaw(b) => Some(b),
+            _ => self.as_str().map(str::as_bytes)
+        }
+    }
+}
+impl core::fmt::Display for MsgPackValue {
+    /// Renders a compact JSON-ish single line meant for logging
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// let input = vec![0x82, 0xA1, 0x6B, 0x92, 0x01, 0xC0, 0xA1, 0x62, 0xC4, 0x02, 0xDE, 0xAD];
+    /// let value = rmpp::unpack(&input).unwrap();
+    /// 
+    /// assert_eq!(r###"{"k": [1, null], "b": b"<2 bytes>"}"###, value.data.to_string());
+    /// ```
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            // Null
+            MsgPackValue::Null => write!(f, "null"),
+            // Boolean
+            MsgPackValue::Bool(b) => write!(f, "{}", b),
+            // Integer
+            MsgPackValue::FixPos(n)|MsgPackValue::U8(n) => write!(f, "{}", n),
+            MsgPackValue::FixNeg(n)|MsgPackValue::I8(n) => write!(f, "{}", n),
+            MsgPackValue::U16(n) => write!(f, "{}", n),
+            MsgPackValue::U32(n) => write!(f, "{}", n),
+            MsgPackValue::U64(n) => write!(f, "{}", n),
+            MsgPackValue::I16(n) => write!(f, "{}", n),
+            MsgPackValue::I32(n) => write!(f, "{}", n),
+            MsgPackValue::I64(n) => write!(f, "{}", n),
+            // Float - Debug keeps the decimal point
+            MsgPackValue::F32(n) => write!(f, "{:?}", n),
+            MsgPackValue::F64(n) => write!(f, "{:?}", n),
+            // String
+            MsgPackValue::FixStr(s)|MsgPackValue::Str8(s)|
+            MsgPackValue::Str16(s)|MsgPackValue::Str32(s) => write!(f, "{:?}", s),
+            MsgPackValue::StrRaw(b) => write!(f, "{:?}", String::from_utf8_lossy(b)),
+            // Binary
+            MsgPackValue::Bin8(b)|MsgPackValue::Bin16(b)|MsgPackValue::Bin32(b) => write!(f, "b\"<{} bytes>\"", b.len()),
+            // Array
+            MsgPackValue::FixArray(values)|MsgPackValue::Array16(values)|MsgPackValue::Array32(values) => {
+                write!(f, "[")?;
+                for (i, v) in values.iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{}", v.data)?;
+                }
+                write!(f, "]")
+            },
+            // Map
+            MsgPackValue::FixMap(values)|MsgPackValue::Map16(values)|MsgPackValue::Map32(values) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in values.iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{}: {}", k.data, v.data)?;
+                }
+                write!(f, "}}")
+            },
+            // Extension
+            MsgPackValue::Ext(t, b) => write!(f, "ext({}, b\"<{} bytes>\")", t, b.len()),
+            MsgPackValue::Timestamp { seconds, nanos } => write!(f, "timestamp({}.{:09})", seconds, nanos),
+        }
+    }
 }
 impl MsgValue for MsgPackValue {
     fn get_value(&self) -> &MsgPackValue {
         self
     }
 }
+impl From<i32> for MsgPackValue {
+    /// Picks the narrowest integer, non-negative ones use the unsigned family
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// use rmpp::MsgPackValue;
+    /// 
+    /// assert_eq!(MsgPackValue::FixPos(42), MsgPackValue::from(42));
+    /// assert_eq!(MsgPackValue::I16(-200), MsgPackValue::from(-200));
+    /// assert_eq!(MsgPackValue::U16(300), 300.into());
+    /// ```
+    fn from(n: i32) -> Self {
+        int2value(i64::from(n))
+    }
+}
+impl From<i64> for MsgPackValue {
+    /// Picks the narrowest integer, non-negative ones use the unsigned family
+    fn from(n: i64) -> Self {
+        int2value(n)
+    }
+}
+impl From<u64> for MsgPackValue {
+    /// Picks the narrowest unsigned integer
+    fn from(n: u64) -> Self {
+        uint2value(n)
+    }
+}
+impl From<f64> for MsgPackValue {
+    /// Always picks F64, never narrowing to F32
+    fn from(n: f64) -> Self {
+        MsgPackValue::F64(n)
+    }
+}
+impl From<bool> for MsgPackValue {
+    fn from(b: bool) -> Self {
+        MsgPackValue::Bool(b)
+    }
+}
+impl From<&str> for MsgPackValue {
+    /// Picks the narrowest string
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// use rmpp::MsgPackValue;
+    /// 
+    /// assert_eq!(MsgPackValue::FixStr("hi".to_string()), "hi".into());
+    /// assert_eq!(MsgPackValue::Str8("x".repeat(32)), MsgPackValue::from("x".repeat(32)));
+    /// ```
+    fn from(s: &str) -> Self {
+        str2value(s.to_string())
+    }
+}
+impl From<String> for MsgPackValue {
+    /// Picks the narrowest string
+    fn from(s: String) -> Self {
+        str2value(s)
+    }
+}
+impl From<Vec<u8>> for MsgPackValue {
+    /// Picks the narrowest binary, Bin8 at least since binary has no fixed-length marker
+    fn from(b: Vec<u8>) -> Self {
+        bin2value(b)
+    }
+}
+
+/// Compares an integer to a float by their exact values, placing zero right after -0.0
+fn cmp_int_float(i: i128, f: f64) -> Ordering {
+    // NaN goes beyond infinity, matching its sign like in f64::total_cmp
+    if f.is_nan() { return if f.is_sign_negative() { Ordering::Greater } else { Ordering::Less }; }
+    if f >= i128::MAX as f64 { return Ordering::Less; } // Rounds up to 2^127
+    if f < i128::MIN as f64 { return Ordering::Greater; }
+
+    // The integer part of a float is a float too, so it converts back exactly
+    let t: i128 = f as i128;
+    match i.cmp(&t) {
+        Ordering::Equal => {
+            let fract: f64 = f - t as f64;
+            if fract > 0.0 { Ordering::Less }
+            else if fract < 0.0 || f.is_sign_negative() && f == 0.0 { Ordering::Greater }
+            else { Ordering::Equal }
+        },
+        o => o
+    }
+}
 
 /// Basic type used for easier JS integration
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// 
+/// Serializes to the variant name as a plain string, which is the contract JavaScript code matches on. 
+/// Names are never changed or removed, new ones are only ever added, 
+///     so frontends should treat a name they don't know as an opaque value rather than fail. 
+/// `Ext` covers both the extensions and the timestamps
+/// 
+/// # Examples 
+/// 
+/// ```
+/// use rmpp::BasicTypes;
+/// 
+/// let names = [
+///     (BasicTypes::Null, "Null"), (BasicTypes::Bool, "Bool"), (BasicTypes::Number, "Number"), 
+///     (BasicTypes::String, "String"), (BasicTypes::Bin, "Bin"), (BasicTypes::Array, "Array"), 
+///     (BasicTypes::Map, "Map"), (BasicTypes::Ext, "Ext"),
+/// ];
+/// for (basic_type, name) in names {
+///     assert_eq!(format!("\"{}\"", name), serde_json::to_string(&basic_type).unwrap());
+///     assert_eq!(basic_type, serde_json::from_str(&format!("\"{}\"", name)).unwrap());
+/// }
+/// 
+/// let input = vec![0xD4, 0x01, 0x00];
+/// assert!(rmpp::unpack_json(&input, Some(false)).unwrap().contains(r###""basic_type":"Ext""###));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum BasicTypes {
     Null, Bool, Number, String, 
-    Bin, Array, Map, // Ext
+    Bin, Array, Map, Ext
 }
 
 fn value2type(value: &MsgPackValue) -> BasicTypes {
@@ -70,32 +1240,340 @@ fn value2type(value: &MsgPackValue) -> BasicTypes {
         MsgPackValue::F32(_)|MsgPackValue::F64(_) => BasicTypes::Number,
         // String
         MsgPackValue::FixStr(_)|MsgPackValue::Str8(_)|
-        MsgPackValue::Str16(_)|MsgPackValue::Str32(_) => BasicTypes::String,
+        MsgPackValue::Str16(_)|MsgPackValue::Str32(_)|MsgPackValue::StrRaw(_) => BasicTypes::String,
         // Binary
         MsgPackValue::Bin8(_)|MsgPackValue::Bin16(_)|MsgPackValue::Bin32(_) => BasicTypes::Bin,
         // Array
         MsgPackValue::FixArray(_)|MsgPackValue::Array16(_)|MsgPackValue::Array32(_) => BasicTypes::Array,
         // Map
         MsgPackValue::FixMap(_)|MsgPackValue::Map16(_)|MsgPackValue::Map32(_) => BasicTypes::Map,
-        // MsgPackValue::Ext(_,_) => BasicTypes::Ext
+        // Extension
+        MsgPackValue::Ext(_,_)|MsgPackValue::Timestamp { .. } => BasicTypes::Ext
     }
 }
 
+/// Splits a JSON Pointer path into unescaped segments, None if it's neither empty nor starts with `/`
+fn pointer_segments(path: &str) -> Option<impl Iterator<Item = String> + '_> {
+    let segments = match path {
+        "" => None,
+        _ => Some(path.strip_prefix('/')?.split('/'))
+    };
+    Some(segments.into_iter().flatten().map(|s| s.replace("~1", "/").replace("~0", "~")))
+}
+
+/// Lists the scalars and empty collections under a value, see `MsgPackEntry::flatten`
+fn flatten_into(value: &MsgPackValue, path: &mut String, res: &mut Vec<(String, MsgPackValue)>) {
+    let len: usize = path.len();
+
+    match (value.as_array(), value.as_map()) {
+        (Some(values), _) if !values.is_empty() => {
+            for (i, v) in values.iter().enumerate() {
+                let _ = write!(path, "/{}", i); // Writing to a String never fails
+                flatten_into(&v.data, path, res);
+                path.truncate(len);
+            }
+        },
+        (_, Some(values)) if !values.is_empty() => {
+            for (k, v) in values {
+                let _ = match k.data.as_str() {
+                    Some(key) => write!(path, "/{}", key.replace('~', "~0").replace('/', "~1")),
+                    None => write!(path, "/~[{}]", k.data)
+                };
+                flatten_into(&v.data, path, res);
+                path.truncate(len);
+            }
+        },
+        _ => res.push((path.clone(), value.clone()))
+    }
+}
+
+/// Puts a value at the end of a path of unescaped JSON Pointer segments, see `MsgPackEntry::set`
+fn pointer_set(entry: &mut MsgPackEntry, segments: &[String], value: MsgPackEntry) -> Result<(), MsgPackError> {
+    let Some((segment, rest)) = segments.split_first() else {
+        *entry = value;
+        return Ok(());
+    };
+
+    let child: &mut MsgPackEntry = match &mut entry.data {
+        MsgPackValue::FixArray(values)|MsgPackValue::Array16(values)|MsgPackValue::Array32(values) => {
+            let index: usize = match segment.as_str() {
+                "-" => values.len(),
+                _ => pointer_index(segment)
+                    .ok_or_else(|| MsgPackError::Custom(format!("Array index expected, got {:?}", segment)))?
+            };
+            if index >= values.len() {
+                values.resize(index, MsgPackEntry::default());
+                values.push(MsgPackEntry::from(MsgPackValue::FixMap(vec![])));
+            }
+            &mut values[index]
+        },
+        MsgPackValue::FixMap(values)|MsgPackValue::Map16(values)|MsgPackValue::Map32(values) => {
+            let index: usize = match values.iter().position(|(k, _)| k.data.as_str() == Some(segment)) {
+                Some(index) => index,
+                None => {
+                    let key = MsgPackEntry::from(str2value(segment.clone()));
+                    values.push((key, MsgPackEntry::from(MsgPackValue::FixMap(vec![]))));
+                    values.len() - 1
+                }
+            };
+            &mut values[index].1
+        },
+        value => return Err(MsgPackError::Custom(format!("Can't step into {} with {:?}", value.marker_name(), segment)))
+    };
+    pointer_set(child, rest, value)?;
+
+    fit_length(&mut entry.data);
+    entry.raw_marker = value2marker(&entry.data);
+    Ok(())
+}
+
+/// Widens an array or a map that outgrew the length field of its marker
+fn fit_length(value: &mut MsgPackValue) {
+    let fits: bool = match value {
+        MsgPackValue::FixArray(values) => values.len() <= 15,
+        MsgPackValue::Array16(values) => values.len() <= u16::MAX as usize,
+        MsgPackValue::FixMap(values) => values.len() <= 15,
+        MsgPackValue::Map16(values) => values.len() <= u16::MAX as usize,
+        _ => true
+    };
+    if fits { return; }
+
+    *value = match core::mem::take(value) {
+        MsgPackValue::FixArray(values)|MsgPackValue::Array16(values) => array2value(values),
+        MsgPackValue::FixMap(values)|MsgPackValue::Map16(values) => map2value(values),
+        value => value
+    };
+}
+
+/// Parses a JSON Pointer array index, which has no sign and no leading zeros
+fn pointer_index(segment: &str) -> Option<usize> {
+    if segment.is_empty() || (segment.len() > 1 && segment.starts_with('0')) || !segment.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    segment.parse().ok()
+}
+
+/// Number of leading bytes shown for binary in a pretty tree
+const TREE_HEX_PREVIEW: usize = 8;
+
+/// Writes a node of a pretty tree and its children, one line each
+fn write_tree(tree: &mut String, value: &MsgPackValue, depth: usize, prefix: &str) {
+    let name: &str = value.marker_name();
+    let preview = |b: &[u8]| {
+        let more: &str = if b.len() > TREE_HEX_PREVIEW { "…" } else { "" };
+        format!("{} bytes: {}{}", b.len(), to_hex(&b[..b.len().min(TREE_HEX_PREVIEW)]), more)
+    };
+
+    // Writing to a String never fails
+    let _ = write!(tree, "{:indent$}{}", "", prefix, indent = depth * 2);
+    let _ = match value {
+        MsgPackValue::Null => writeln!(tree, "{}", name),
+        MsgPackValue::Bin8(b)|MsgPackValue::Bin16(b)|MsgPackValue::Bin32(b) => writeln!(tree, "{}({})", name, preview(b)),
+        MsgPackValue::Ext(t, b) => writeln!(tree, "{}({}, {})", name, t, preview(b)),
+        MsgPackValue::FixArray(values)|MsgPackValue::Array16(values)|MsgPackValue::Array32(values) => {
+            let _ = writeln!(tree, "{}({})", name, values.len());
+            for v in values { write_tree(tree, &v.data, depth + 1, ""); }
+            Ok(())
+        },
+        MsgPackValue::FixMap(values)|MsgPackValue::Map16(values)|MsgPackValue::Map32(values) => {
+            let _ = writeln!(tree, "{}({})", name, values.len());
+            for (k, v) in values { write_tree(tree, &v.data, depth + 1, &format!("{}: ", k.data)); }
+            Ok(())
+        },
+        _ => writeln!(tree, "{}({})", name, value)
+    };
+}
+
+/// Returns the marker byte a value gets encoded with
+pub(crate) fn value2marker(value: &MsgPackValue) -> u8 {
+    match value {
+        // Null
+        MsgPackValue::Null => markers::NULL,
+        // Boolean
+        MsgPackValue::Bool(b) => if !*b { markers::FALSE } else { markers::TRUE },
+        // Fixed Integer
+        MsgPackValue::FixPos(n) => (*n) & 0b0111_1111,
+        MsgPackValue::FixNeg(n) => (*n as u8) & 0b0001_1111 | 0b1110_0000,
+        // Unsigned Integer
+        MsgPackValue::U8(_)  => markers::U8,
+        MsgPackValue::U16(_) => markers::U16,
+        MsgPackValue::U32(_) => markers::U32,
+        MsgPackValue::U64(_) => markers::U64,
+        // Signed Integer
+        MsgPackValue::I8(_)  => markers::I8,
+        MsgPackValue::I16(_) => markers::I16,
+        MsgPackValue::I32(_) => markers::I32,
+        MsgPackValue::I64(_) => markers::I64,
+        // Float
+        MsgPackValue::F32(_) => markers::F32,
+        MsgPackValue::F64(_) => markers::F64,
+        // String
+        MsgPackValue::FixStr(s) => (s.len() as u8) & 0b0001_1111 | 0b1010_0000,
+        MsgPackValue::Str8(_)  => markers::STR8,
+        MsgPackValue::Str16(_) => markers::STR16,
+        MsgPackValue::Str32(_) => markers::STR32,
+        MsgPackValue::StrRaw(b) => match b.len() {
+            len if len <= 31 => (len as u8) | 0b1010_0000,
+            len if len <= u8::MAX as usize  => markers::STR8,
+            len if len <= u16::MAX as usize => markers::STR16,
+            _ => markers::STR32
+        },
+        // Binary
+        MsgPackValue::Bin8(_)  => markers::BIN8,
+        MsgPackValue::Bin16(_) => markers::BIN16,
+        MsgPackValue::Bin32(_) => markers::BIN32,
+        // Array
+        MsgPackValue::FixArray(values) => (values.len() as u8) & 0b0000_1111 | 0b1001_0000,
+        MsgPackValue::Array16(_) => markers::ARRAY16,
+        MsgPackValue::Array32(_) => markers::ARRAY32,
+        // Map
+        MsgPackValue::FixMap(values) => (values.len() as u8) & 0b0000_1111 | 0b1000_0000,
+        MsgPackValue::Map16(_) => markers::MAP16,
+        MsgPackValue::Map32(_) => markers::MAP32,
+        // Extension - FixExt is used whenever the payload length allows it
+        MsgPackValue::Ext(_, b) => match b.len() {
+            1  => markers::FIXEXT1,
+            2  => markers::FIXEXT2,
+            4  => markers::FIXEXT4,
+            8  => markers::FIXEXT8,
+            16 => markers::FIXEXT16,
+            len if len <= u8::MAX as usize  => markers::EXT8,
+            len if len <= u16::MAX as usize => markers::EXT16,
+            _ => markers::EXT32
+        },
+        // Timestamp - the smallest layout that can hold the value is used
+        MsgPackValue::Timestamp { seconds, nanos } => {
+            if *nanos == 0 && (0..=u32::MAX as i64).contains(seconds) { markers::FIXEXT4 }      // Timestamp 32
+            else if (0..1i64 << 34).contains(seconds) { markers::FIXEXT8 }                      // Timestamp 64
+            else { markers::EXT8 }                                                           // Timestamp 96
+        }
+    }
+}
+
+/// Returns the narrowest value holding an unsigned integer
+pub(crate) fn uint2value(n: u64) -> MsgPackValue {
+    if n <= 0x7F { MsgPackValue::FixPos(n as u8) }
+    else if n <= u8::MAX as u64  { MsgPackValue::U8(n as u8) }
+    else if n <= u16::MAX as u64 { MsgPackValue::U16(n as u16) }
+    else if n <= u32::MAX as u64 { MsgPackValue::U32(n as u32) }
+    else { MsgPackValue::U64(n) }
+}
+
+/// Returns the narrowest value holding a signed integer
+/// 
+/// Non-negative integers use the unsigned family
+pub(crate) fn int2value(n: i64) -> MsgPackValue {
+    if n >= 0 { uint2value(n as u64) }
+    else if n >= -32 { MsgPackValue::FixNeg(n as i8) }
+    else if n >= i8::MIN as i64  { MsgPackValue::I8(n as i8) }
+    else if n >= i16::MIN as i64 { MsgPackValue::I16(n as i16) }
+    else if n >= i32::MIN as i64 { MsgPackValue::I32(n as i32) }
+    else { MsgPackValue::I64(n) }
+}
+
+/// Returns the narrowest value holding a string
+pub(crate) fn str2value(s: String) -> MsgPackValue {
+    let len: usize = s.len();
+    if len <= 31 { MsgPackValue::FixStr(s) }
+    else if len <= u8::MAX as usize  { MsgPackValue::Str8(s) }
+    else if len <= u16::MAX as usize { MsgPackValue::Str16(s) }
+    else { MsgPackValue::Str32(s) }
+}
+
+/// Returns the narrowest value holding binary
+pub(crate) fn bin2value(b: Vec<u8>) -> MsgPackValue {
+    let len: usize = b.len();
+    if len <= u8::MAX as usize { MsgPackValue::Bin8(b) }
+    else if len <= u16::MAX as usize { MsgPackValue::Bin16(b) }
+    else { MsgPackValue::Bin32(b) }
+}
+
+/// Returns the narrowest value holding an array
+pub(crate) fn array2value(values: Vec<MsgPackEntry>) -> MsgPackValue {
+    let len: usize = values.len();
+    if len <= 15 { MsgPackValue::FixArray(values) }
+    else if len <= u16::MAX as usize { MsgPackValue::Array16(values) }
+    else { MsgPackValue::Array32(values) }
+}
+
+/// Returns the narrowest value holding a map
+pub(crate) fn map2value(values: Vec<(MsgPackEntry, MsgPackEntry)>) -> MsgPackValue {
+    let len: usize = values.len();
+    if len <= 15 { MsgPackValue::FixMap(values) }
+    else if len <= u16::MAX as usize { MsgPackValue::Map16(values) }
+    else { MsgPackValue::Map32(values) }
+}
+
+/// Error of the json functions, a JsValue when built for JavaScript
+#[cfg(feature = "wasm")]
+pub type JsonError = wasm_bindgen::JsValue;
+/// Error of the json functions, a JsValue when built for JavaScript
+#[cfg(not(feature = "wasm"))]
+pub type JsonError = MsgPackError;
+
+/// Turns any error into a JsonError
+#[cfg(feature = "wasm")]
+pub(crate) fn json_error<E: core::fmt::Display>(e: E) -> JsonError {
+    wasm_bindgen::JsValue::from_str(&e.to_string())
+}
+/// Turns any error into a JsonError
+#[cfg(not(feature = "wasm"))]
+pub(crate) fn json_error<E: core::fmt::Display>(e: E) -> JsonError {
+    MsgPackError::Custom(e.to_string())
+}
+
 /// Handles errors
 #[derive(Debug)]
 pub enum MsgPackError {
     Io(io::Error),
+    /// The data ended in the middle of a value, more bytes might complete it
+    Truncated,
     Custom(String),
+    /// Decoding a buffer failed at the given byte offset, right past the bytes that caused the error
+    At { offset: usize, source: Box<MsgPackError> },
+}
+impl MsgPackError {
+    /// Returns the byte offset decoding failed at, if it's known
+    /// 
+    /// # Examples 
+    /// 
+    /// ```
+    /// let input = vec![0x92, 0xC3, 0xA2, 0x61, 0xFF];
+    /// let e = rmpp::unpack(&input).unwrap_err();
+    /// 
+    /// assert_eq!(Some(5), e.offset());
+    /// assert!(e.to_string().ends_with(" at offset 5"));
+    /// ```
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            MsgPackError::At { offset, .. } => Some(*offset),
+            _ => None
+        }
+    }
+}
+impl core::error::Error for MsgPackError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            MsgPackError::At { source, .. } => Some(source.as_ref()),
+            _ => None
+        }
+    }
 }
-impl std::error::Error for MsgPackError {}
 impl From<io::Error> for MsgPackError {
-    fn from(e: io::Error) -> Self { MsgPackError::Io(e) }
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => MsgPackError::Truncated,
+            _ => MsgPackError::Io(e)
+        }
+    }
 }
-impl std::fmt::Display for MsgPackError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for MsgPackError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             MsgPackError::Io(e) => write!(f, "IO error: {}", e),
+            MsgPackError::Truncated => write!(f, "Unexpected end of data"),
             MsgPackError::Custom(s) => write!(f, "{}", s),
+            MsgPackError::At { offset, source } => write!(f, "{} at offset {}", source, offset),
         }
     }
 }
\ No newline at end of file